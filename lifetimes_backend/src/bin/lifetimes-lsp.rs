@@ -0,0 +1,251 @@
+//! Language server for the lifetimes extension, speaking LSP over stdio.
+//!
+//! Open documents are checked whenever they change and borrow errors are published as diagnostics.
+//! The custom `lifetimes/ranges` request (params: `{ textDocument: { uri } }`) returns the lifetimes of every
+//! function in a document, including the lanes for rendering them in the gutter.
+
+use std::{
+    error::Error,
+    panic::{self, AssertUnwindSafe},
+};
+
+use lifetimes_backend::{CheckError, CheckerError, Session};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, Location, NumberOrString, Position,
+    PublishDiagnosticsParams, Range, TextDocumentIdentifier, Url,
+};
+use serde::Deserialize;
+use serde_json::json;
+use syntax::TextRange;
+
+const RANGES_METHOD: &str = "lifetimes/ranges";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RangesParams {
+    text_document: TextDocumentIdentifier,
+}
+
+type ServerResult<T> = Result<T, Box<dyn Error + Sync + Send>>;
+
+fn main() -> ServerResult<()> {
+    // stdout belongs to the protocol, so everything else goes to stderr
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Warn)
+        .init();
+
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(json!({
+        // Full document sync
+        "textDocumentSync": 1,
+    }))?;
+
+    Server {
+        connection,
+        session: Session::new(),
+    }
+    .run()?;
+
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server {
+    connection: Connection,
+    session: Session,
+}
+
+impl Server {
+    fn run(&mut self) -> ServerResult<()> {
+        let receiver = self.connection.receiver.clone();
+        for message in &receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        match request.method.as_str() {
+            RANGES_METHOD => match serde_json::from_value::<RangesParams>(request.params) {
+                Ok(params) => self.ranges(request.id, &params.text_document.uri),
+                Err(error) => invalid_params(request.id, error),
+            },
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown request {}", request.method),
+            ),
+        }
+    }
+
+    fn ranges(&self, id: RequestId, uri: &Url) -> Response {
+        let path = document_path(uri);
+        if !self.session.contains_file(&path) {
+            return invalid_params(id, format!("{} is not open", uri));
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(|| self.session.lifetimes(&path))) {
+            Ok((functions, _)) => Response::new_ok(id, functions),
+            Err(_) => Response::new_err(
+                id,
+                ErrorCode::InternalError as i32,
+                format!("checking {} failed", uri),
+            ),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> ServerResult<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.session
+                    .set_file(&document_path(&document.uri), document.text);
+                self.publish_diagnostics(document.uri, Some(document.version))?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // With full sync, the last change contains the whole document
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let uri = params.text_document.uri;
+                    self.session.set_file(&document_path(&uri), change.text);
+                    self.publish_diagnostics(uri, Some(params.text_document.version))?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.session.remove_file(&document_path(&uri));
+                self.send_diagnostics(uri, Vec::new(), None)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: Url, version: Option<i32>) -> ServerResult<()> {
+        let path = document_path(&uri);
+        let diagnostics = match panic::catch_unwind(AssertUnwindSafe(|| self.session.check(&path)))
+        {
+            Ok(Ok(())) => Vec::new(),
+            Ok(Err(error)) => vec![diagnostic(&error, &uri, &self.session.text(&path))],
+            Err(_) => {
+                // Unsupported syntax makes the checker panic, keep serving the other documents
+                log::error!("checking {} panicked", uri);
+                Vec::new()
+            }
+        };
+        self.send_diagnostics(uri, diagnostics, version)
+    }
+
+    fn send_diagnostics(
+        &self,
+        uri: Url,
+        diagnostics: Vec<Diagnostic>,
+        version: Option<i32>,
+    ) -> ServerResult<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        };
+        self.connection
+            .sender
+            .send(Message::Notification(Notification::new(
+                PublishDiagnostics::METHOD.to_string(),
+                params,
+            )))?;
+        Ok(())
+    }
+}
+
+/// Documents are stored in the session under the path of their URI, e.g. `/src/main.rs` for
+/// `file:///src/main.rs`. Documents of other schemes, like unsaved (`untitled:`) ones, are stored under
+/// `/<scheme>/`.
+fn document_path(uri: &Url) -> String {
+    match uri.scheme() {
+        "file" => uri.path().to_string(),
+        scheme => format!("/{}/{}", scheme, uri.path().trim_start_matches('/')),
+    }
+}
+
+fn invalid_params(id: RequestId, error: impl ToString) -> Response {
+    Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string())
+}
+
+fn diagnostic(error: &CheckError, uri: &Url, text: &str) -> Diagnostic {
+    let mut related_information = Vec::new();
+    if let CheckError::Borrowcheck {
+        error: CheckerError::Invalid(used, _, invalidation),
+        range,
+    } = error
+    {
+        for step in &invalidation.chain {
+            if let Some(step_range) = step.range {
+                related_information.push(DiagnosticRelatedInformation {
+                    location: Location::new(uri.clone(), lsp_range(text, step_range)),
+                    message: format!(
+                        "`{}` borrows `{}` {}",
+                        step.borrower,
+                        step.borrowed,
+                        if step.is_mut { "mutably" } else { "immutably" }
+                    ),
+                });
+            }
+        }
+        related_information.push(DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), lsp_range(text, *range)),
+            message: format!("`{}` used here", used),
+        });
+    }
+
+    Diagnostic {
+        range: error
+            .range()
+            .map_or_else(Range::default, |range| lsp_range(text, range)),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: error
+            .code()
+            .map(|code| NumberOrString::String(code.to_string())),
+        source: Some("lifetimes".to_string()),
+        message: error.to_string(),
+        related_information: Some(related_information).filter(|related| !related.is_empty()),
+        ..Diagnostic::default()
+    }
+}
+
+fn lsp_range(text: &str, range: TextRange) -> Range {
+    Range::new(
+        position(text, range.start().into()),
+        position(text, range.end().into()),
+    )
+}
+
+/// LSP positions count UTF-16 code units within a line
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].encode_utf16().count() as u32,
+    )
+}
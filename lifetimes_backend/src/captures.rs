@@ -0,0 +1,197 @@
+//! Capture analysis for closures. Like edition 2021, a closure captures the places its body mentions (`x.a`
+//! rather than `x`), each in the least powerful mode its uses require. Unless the closure is a `move` closure,
+//! places that are only copied are captured by shared reference.
+
+use hir::{db::HirDatabase, Semantics};
+use syntax::{
+    ast::{self, AstNode},
+    SyntaxNode, TextRange,
+};
+
+use crate::{is_copy, resolve_local_ref, signature::Generics};
+
+/// Ordered from the least to the most powerful mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum CaptureMode {
+    /// The place is only read
+    Shared,
+    /// The place is assigned to, borrowed mutably or written through
+    Unique,
+    /// The place is moved (not copied), or the closure (or a closure nested in it) is a `move` closure
+    ByValue,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Capture {
+    pub local: hir::Local,
+    /// The captured place, e.g. `x` or `x.a.b`
+    pub place: String,
+    pub mode: CaptureMode,
+    /// The first use of the place that requires `mode`
+    pub range: TextRange,
+}
+
+/// The places of locals mentioned in the closure's body. This includes the closure's parameters and the locals
+/// declared in its body, which callers have to skip. A place is only captured once: if both `x` and `x.a` are used,
+/// `x` is captured in the stronger of their modes.
+pub(crate) fn closure_captures<'db, DB: HirDatabase>(
+    closure: &ast::ClosureExpr,
+    sema: &Semantics<'db, DB>,
+) -> Vec<Capture> {
+    let body = match closure.body() {
+        Some(body) => body,
+        None => return Vec::new(),
+    };
+
+    let generics = Generics::enclosing(closure.syntax());
+    let mut captures: Vec<Capture> = Vec::new();
+    for path in body.syntax().descendants().filter_map(ast::PathExpr::cast) {
+        // Paths that don't resolve are reported when the body is checked
+        let local = match path
+            .path()
+            .and_then(|path| resolve_local_ref(path, sema).ok().flatten())
+        {
+            Some(local) => local,
+            None => continue,
+        };
+
+        // `x.a.b` is captured as a whole
+        let mut place_node = path.syntax().clone();
+        let mut place = path.syntax().text().to_string();
+        while let Some(field) = place_node.parent().and_then(ast::FieldExpr::cast) {
+            match field.name_ref() {
+                Some(name) => place += &format!(".{}", name),
+                None => break,
+            }
+            place_node = field.syntax().clone();
+        }
+
+        let is_moved_into_closure = place_node
+            .ancestors()
+            .take_while(|node| node != closure.syntax())
+            .chain(std::iter::once(closure.syntax().clone()))
+            .filter_map(ast::ClosureExpr::cast)
+            .any(|closure| closure.move_token().is_some());
+        let mode = if is_moved_into_closure {
+            CaptureMode::ByValue
+        } else {
+            match use_mode(&place_node, sema) {
+                // Copying the place out only needs to read it
+                CaptureMode::ByValue
+                    if ast::Expr::cast(place_node.clone())
+                        .and_then(|place| sema.type_of_expr(&place))
+                        .map_or(false, |ty| is_copy(&ty.original(), &generics, sema.db)) =>
+                {
+                    CaptureMode::Shared
+                }
+                mode => mode,
+            }
+        };
+
+        let capture = Capture {
+            local,
+            place,
+            mode,
+            range: place_node.text_range(),
+        };
+        match captures
+            .iter_mut()
+            .find(|captured| captured.local == local && captured.place == capture.place)
+        {
+            Some(captured) if captured.mode < capture.mode => *captured = capture,
+            Some(_) => {}
+            None => captures.push(capture),
+        }
+    }
+
+    // Captured ancestors take over the captures of their fields
+    captures.sort_by(|a, b| a.place.len().cmp(&b.place.len()));
+    let mut merged: Vec<Capture> = Vec::new();
+    for capture in captures {
+        match merged.iter_mut().find(|ancestor| {
+            ancestor.local == capture.local
+                && capture
+                    .place
+                    .strip_prefix(&ancestor.place)
+                    .map_or(false, |rest| rest.starts_with('.'))
+        }) {
+            Some(ancestor) if ancestor.mode < capture.mode => {
+                ancestor.mode = capture.mode;
+                ancestor.range = capture.range;
+            }
+            Some(_) => {}
+            None => merged.push(capture),
+        }
+    }
+    merged.sort_by_key(|capture| capture.range.start());
+    merged
+}
+
+/// The mode a place has to be captured in to be used by its parent expression
+fn use_mode<'db, DB: HirDatabase>(place: &SyntaxNode, sema: &Semantics<'db, DB>) -> CaptureMode {
+    // Parentheses don't change how a place is used
+    let mut node = place.clone();
+    while let Some(paren) = node.parent().and_then(ast::ParenExpr::cast) {
+        node = paren.syntax().clone();
+    }
+    let is_node = |expr: Option<ast::Expr>| expr.map_or(false, |expr| *expr.syntax() == node);
+
+    let parent = match node.parent() {
+        Some(parent) => parent,
+        None => return CaptureMode::ByValue,
+    };
+    if let Some(bin_expr) = ast::BinExpr::cast(parent.clone()) {
+        match bin_expr.op_kind() {
+            Some(ast::BinaryOp::Assignment { .. }) if is_node(bin_expr.lhs()) => {
+                CaptureMode::Unique
+            }
+            Some(ast::BinaryOp::Assignment { .. }) => CaptureMode::ByValue,
+            _ => CaptureMode::Shared,
+        }
+    } else if let Some(ref_expr) = ast::RefExpr::cast(parent.clone()) {
+        if ref_expr.mut_token().is_some() {
+            CaptureMode::Unique
+        } else {
+            CaptureMode::Shared
+        }
+    } else if let Some(prefix_expr) = ast::PrefixExpr::cast(parent.clone()) {
+        match prefix_expr.op_kind() {
+            // Writing through a reference needs a unique borrow of it, reading through it a shared one
+            Some(ast::UnaryOp::Deref)
+                if use_mode(prefix_expr.syntax(), sema) == CaptureMode::Unique =>
+            {
+                CaptureMode::Unique
+            }
+            _ => CaptureMode::Shared,
+        }
+    } else if let Some(method_call) = ast::MethodCallExpr::cast(parent.clone()) {
+        if !is_node(method_call.receiver()) {
+            return CaptureMode::ByValue;
+        }
+        let access = sema
+            .resolve_method_call(&method_call)
+            .and_then(|function| function.self_param(sema.db))
+            .map(|self_param| self_param.access(sema.db));
+        match access {
+            Some(hir::Access::Owned) => CaptureMode::ByValue,
+            Some(hir::Access::Exclusive) => CaptureMode::Unique,
+            Some(hir::Access::Shared) | None => CaptureMode::Shared,
+        }
+    } else if let Some(call) = ast::CallExpr::cast(parent.clone()) {
+        // Calling a captured closure only needs a reference to it
+        if is_node(call.expr()) {
+            CaptureMode::Shared
+        } else {
+            CaptureMode::ByValue
+        }
+    } else if ast::ExprStmt::can_cast(parent.kind())
+        || ast::Condition::can_cast(parent.kind())
+        || ast::IndexExpr::can_cast(parent.kind())
+        || ast::MatchExpr::can_cast(parent.kind())
+    {
+        CaptureMode::Shared
+    } else {
+        // e.g. a `let` initializer, an argument, a returned value or a closure's body
+        CaptureMode::ByValue
+    }
+}
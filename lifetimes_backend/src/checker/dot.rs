@@ -0,0 +1,123 @@
+use std::fmt::Write;
+
+use super::{Borrow, OriginId, VarKind, VarSnapshot, VarStatus};
+
+/// Renders vars and their borrows as a Graphviz graph. Vars are grouped into one cluster per origin.
+///
+/// - Nodes are styled by status: uninitialized vars are dashed, moved ones grey and invalid ones red
+/// - A borrower points to what it borrows, with bold red edges for mutable and blue edges for shared borrows
+/// - Dotted edges lead from a borrowed var back to its borrowers (as recorded in its status)
+/// - Deref vars (`*r`) are connected to the reference they dereference by a dashed edge
+pub fn borrow_graph(vars: &[VarSnapshot]) -> String {
+    let mut dot = String::from("digraph borrows {\n    node [fontname=\"monospace\"];\n");
+
+    let mut origins = Vec::<OriginId>::new();
+    for var in vars {
+        if !origins.contains(&var.origin) {
+            origins.push(var.origin);
+        }
+    }
+    for (index, origin) in origins.iter().enumerate() {
+        writeln!(dot, "    subgraph cluster_{} {{", index).unwrap();
+        writeln!(dot, "        label=\"{}\";", origin).unwrap();
+        for var in vars.iter().filter(|var| var.origin == *origin) {
+            writeln!(dot, "        {};", node(var)).unwrap();
+        }
+        dot += "    }\n";
+    }
+
+    let is_shown = |id| vars.iter().any(|var| var.id == id);
+    for var in vars {
+        for borrow in &var.borrows {
+            let attributes = match borrow {
+                Borrow::Mutable(_) => "label=\"&mut\", color=red, style=bold",
+                Borrow::Immutable(_) => "label=\"&\", color=blue",
+            };
+            if is_shown(borrow.target()) {
+                writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\" [{}];",
+                    var.id,
+                    borrow.target(),
+                    attributes
+                )
+                .unwrap();
+            }
+        }
+
+        let borrowers = match &var.status {
+            VarStatus::Borrowed(borrowers) => borrowers.clone(),
+            VarStatus::MutBorrowed(borrower) => vec![*borrower],
+            VarStatus::Reserved(borrower, readers) => {
+                std::iter::once(*borrower).chain(readers.clone()).collect()
+            }
+            _ => Vec::new(),
+        };
+        for borrower in borrowers.into_iter().filter(|borrower| is_shown(*borrower)) {
+            writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [style=dotted, color=gray40, constraint=false];",
+                var.id, borrower
+            )
+            .unwrap();
+        }
+
+        if let Some(parent) = var.parent.filter(|parent| is_shown(*parent)) {
+            writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"*\", style=dashed, arrowhead=none];",
+                var.id, parent
+            )
+            .unwrap();
+        }
+    }
+
+    dot += "}\n";
+    dot
+}
+
+fn node(var: &VarSnapshot) -> String {
+    let shape = match var.kind {
+        VarKind::Local | VarKind::Param => "box",
+        VarKind::Temporary => "ellipse",
+        VarKind::Literal => "plaintext",
+        VarKind::Static => "octagon",
+        VarKind::Deref => "diamond",
+        VarKind::Field => "component",
+    };
+    let (state, style) = if var.invalidation.is_some() {
+        (" (invalid)", "color=red, fontcolor=red")
+    } else {
+        match var.status {
+            VarStatus::Unitialized => (" (uninitialized)", "style=dashed"),
+            VarStatus::Moved => (
+                " (moved)",
+                "style=filled, fillcolor=gray90, fontcolor=gray40",
+            ),
+            _ => ("", ""),
+        }
+    };
+    let mut label = format!("{} {}{}", var.id, var.identifier, state);
+    if let Some(invalidation) = &var.invalidation {
+        label += &format!("\n{}", invalidation);
+    }
+
+    let mut node = format!(
+        "\"{}\" [label=\"{}\", shape={}",
+        var.id,
+        escape(&label),
+        shape
+    );
+    if !style.is_empty() {
+        node += ", ";
+        node += style;
+    }
+    node + "]"
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
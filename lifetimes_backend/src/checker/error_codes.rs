@@ -0,0 +1,22 @@
+/// Returns the text that `rustc --explain <code>` prints for the borrow checker errors we report
+pub fn explain(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "E0308" => include_str!("error_codes/E0308.md"),
+        "E0381" => include_str!("error_codes/E0381.md"),
+        "E0382" => include_str!("error_codes/E0382.md"),
+        "E0384" => include_str!("error_codes/E0384.md"),
+        "E0499" => include_str!("error_codes/E0499.md"),
+        "E0502" => include_str!("error_codes/E0502.md"),
+        "E0503" => include_str!("error_codes/E0503.md"),
+        "E0505" => include_str!("error_codes/E0505.md"),
+        "E0506" => include_str!("error_codes/E0506.md"),
+        "E0507" => include_str!("error_codes/E0507.md"),
+        "E0515" => include_str!("error_codes/E0515.md"),
+        "E0596" => include_str!("error_codes/E0596.md"),
+        "E0597" => include_str!("error_codes/E0597.md"),
+        "E0700" => include_str!("error_codes/E0700.md"),
+        "E0716" => include_str!("error_codes/E0716.md"),
+        "E0759" => include_str!("error_codes/E0759.md"),
+        _ => return None,
+    })
+}
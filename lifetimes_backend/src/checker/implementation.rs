@@ -1,10 +1,11 @@
 use std::{cell::RefCell, fmt::Display};
 
 use log::{debug, trace};
+use syntax::TextRange;
 
-use super::{CheckerError, CheckerResult};
+use super::{BorrowStep, CheckerError, CheckerResult, Invalidation, InvalidationCause};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct VarId(usize);
 
 impl Display for VarId {
@@ -17,6 +18,7 @@ impl Display for VarId {
 pub struct Vars {
     void_literal: VarId,
     vars: Vec<RefCell<Var>>,
+    loans: RefCell<Vec<Loan>>,
 }
 
 impl Display for Vars {
@@ -34,17 +36,11 @@ impl Vars {
         Self {
             vars: vec![RefCell::new(Var {
                 status: VarStatus::Initialized,
-                valid: true,
-                identifier: "()".to_string(),
-                is_mut: false,
                 is_copy: true,
-                id: VarId(0),
-                deref_var: None,
-                parent: None,
-                borrows: Vec::new(),
-                origin: static_origin,
+                ..Var::new(VarKind::Literal, "()".to_string(), static_origin)
             })],
             void_literal: VarId(0),
+            loans: RefCell::new(Vec::new()),
         }
     }
 
@@ -60,51 +56,65 @@ impl Vars {
         identifier: String,
     ) -> VarId {
         self.add_var(Var {
-            status: VarStatus::Unitialized,
-            valid: true,
             is_mut,
             is_copy,
-            identifier,
-            id: VarId(0),
-            deref_var: None,
-            parent: None,
-            borrows: Vec::new(),
-            origin,
+            ..Var::new(VarKind::Local, identifier, origin)
+        })
+    }
+
+    pub fn create_param(
+        &mut self,
+        origin: OriginId,
+        is_mut: bool,
+        is_copy: bool,
+        identifier: String,
+        range: TextRange,
+    ) -> VarId {
+        self.add_var(Var {
+            status: VarStatus::Initialized,
+            is_mut,
+            is_copy,
+            initialized_at: Some(range),
+            live_range: Some(range),
+            ..Var::new(VarKind::Param, identifier, origin)
         })
     }
 
+    /// For the values of expressions that are not places (e.g. `if` expressions)
+    pub fn create_expr_tmp(&mut self, origin: OriginId, identifier: String) -> VarId {
+        self.add_var(Var::new(VarKind::Temporary, identifier, origin))
+    }
+
     /// For refs (i.e. assumes the value is copy)
     pub fn create_tmp(&mut self, origin: OriginId, is_mut: bool, text: String) -> VarId {
         self.add_var(Var {
-            status: VarStatus::Unitialized,
-            valid: true,
-            identifier: "<tmp> ".to_string() + &text,
             is_mut,
             is_copy: true,
-            id: VarId(0),
-            deref_var: None,
-            parent: None,
-            borrows: Vec::new(),
-            origin,
+            ..Var::new(VarKind::Temporary, "<tmp> ".to_string() + &text, origin)
         })
     }
 
     pub fn create_literal(&mut self, origin: OriginId, literal: String) -> VarId {
         self.add_var(Var {
             status: VarStatus::Initialized,
-            valid: true,
-            identifier: "<lit> ".to_string() + &literal,
-            is_mut: false,
             is_copy: true,
-            id: VarId(0),
-            deref_var: None,
-            parent: None,
-            borrows: Vec::new(),
-            origin,
+            ..Var::new(VarKind::Literal, "<lit> ".to_string() + &literal, origin)
         })
     }
 
-    pub fn get_deref_var(&mut self, derefed_var: VarId) -> VarId {
+    /// For `static` and `const` items, which don't need to be initialized
+    pub fn create_static(&mut self, origin: OriginId, is_mut: bool, identifier: String) -> VarId {
+        self.add_var(Var {
+            status: VarStatus::Initialized,
+            is_mut,
+            is_copy: true,
+            ..Var::new(VarKind::Static, identifier, origin)
+        })
+    }
+
+    /// The var of the value behind the reference `derefed_var`. Moving it out is an error (E0507) unless it is
+    /// `Copy`, see `is_copy`.
+    pub fn get_deref_var(&mut self, derefed_var: VarId, is_copy: bool) -> VarId {
         let deref_var = self.resolve_var(derefed_var).borrow().deref_var;
         if let Some(deref_var) = deref_var {
             deref_var
@@ -113,22 +123,242 @@ impl Vars {
             let identifier = "*".to_string() + &self.resolve_var(derefed_var).borrow().identifier;
             let origin = self.resolve_var(derefed_var).borrow().origin;
             let deref_var = self.add_var(Var {
-                status: VarStatus::Unitialized,
-                valid: true,
-                identifier,
                 is_mut,
-                is_copy: false, // TODO TODO TODO
-                id: VarId(0),
-                deref_var: None,
+                is_copy,
                 parent: Some(derefed_var),
-                borrows: Vec::new(),
-                origin,
+                ..Var::new(VarKind::Deref, identifier, origin)
             });
             self.resolve_var(derefed_var).borrow_mut().deref_var = Some(deref_var);
             deref_var
         }
     }
 
+    /// The var of the place `path` (e.g. `a.b`) inside `base`, borrowed instead of all of `base` by the closures
+    /// capturing that place. Borrows of disjoint places don't conflict, while an access to a place conflicts with the
+    /// borrows of the places inside it and of the places containing it.
+    pub fn get_field_var(&mut self, base: VarId, path: &str) -> VarId {
+        let (name, rest) = match path.split_once('.') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (path, None),
+        };
+        let existing = self
+            .resolve_var(base)
+            .borrow()
+            .fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, var)| *var);
+        let field_var = match existing {
+            Some(field_var) => field_var,
+            None => {
+                let (is_mut, identifier, origin) = {
+                    let base = self.resolve_var(base).borrow();
+                    (
+                        base.is_mut,
+                        format!("{}.{}", base.identifier, name),
+                        base.origin,
+                    )
+                };
+                let field_var = self.add_var(Var {
+                    status: VarStatus::Initialized,
+                    is_mut,
+                    base: Some(base),
+                    ..Var::new(VarKind::Field, identifier, origin)
+                });
+                self.resolve_var(base)
+                    .borrow_mut()
+                    .fields
+                    .push((name.to_string(), field_var));
+                field_var
+            }
+        };
+        match rest {
+            Some(rest) => self.get_field_var(field_var, rest),
+            None => field_var,
+        }
+    }
+
+    /// Invalidates all borrowers of the vars that live in `origin`, because they are dropped when it ends.
+    /// `except` is the var that is moved out of the origin (if any).
+    pub fn drop_origin(&self, origin: OriginId, except: Option<VarId>, range: TextRange) {
+        for var in &self.vars {
+            let var = var.borrow();
+            // Deref vars don't own their value
+            if var.origin == origin && var.parent.is_none() && Some(var.id) != except {
+                debug!("Dropping {}", var.id);
+                var.invalidate_borrowers(InvalidationCause::Dropped, range, self);
+            }
+        }
+    }
+
+    /// Invalidates all borrowers of the temporaries, which are dropped at the end of their statement (`range`)
+    pub fn drop_temporaries(&self, temporaries: &[VarId], range: TextRange) {
+        for temporary in temporaries {
+            let var = self.resolve_var(*temporary).borrow();
+            debug!("Dropping temporary {}", var.id);
+            var.invalidate_borrowers(InvalidationCause::TemporaryDropped, range, self);
+        }
+    }
+
+    /// The state of all vars for which `is_live` returns true for their origin
+    pub fn snapshot(&self, is_live: impl Fn(OriginId) -> bool) -> Vec<VarSnapshot> {
+        self.vars
+            .iter()
+            .map(|var| var.borrow())
+            .filter(|var| var.id != self.void_literal && is_live(var.origin))
+            .map(|var| VarSnapshot {
+                id: var.id,
+                identifier: var.identifier.clone(),
+                kind: var.kind,
+                status: var.status.clone(),
+                borrows: var.borrows.clone(),
+                invalidation: var.invalidation.clone(),
+                origin: var.origin,
+                parent: var.parent,
+                initialized_at: var.initialized_at,
+                live_range: var.live_range,
+            })
+            .collect()
+    }
+
+    /// The state of all vars and loans, see `restore`
+    pub fn save(&self) -> VarsState {
+        VarsState {
+            vars: self.vars.iter().map(|var| var.borrow().clone()).collect(),
+            loans: self.loans.borrow().clone(),
+        }
+    }
+
+    /// Resets the vars and loans that existed when `state` was saved. Vars and loans created since then are kept, and
+    /// the live ranges keep covering the uses since then.
+    pub fn restore(&self, state: VarsState) {
+        for saved in state.vars {
+            let mut var = self.resolve_var(saved.id).borrow_mut();
+            *var = Var {
+                live_range: var.live_range,
+                ..saved
+            };
+        }
+        let mut loans = self.loans.borrow_mut();
+        for saved in state.loans {
+            let loan = &mut loans[saved.id.0];
+            *loan = Loan {
+                live_range: loan.live_range,
+                ..saved
+            };
+        }
+    }
+
+    /// All loans created so far
+    pub fn loans(&self) -> Vec<Loan> {
+        self.loans.borrow().clone()
+    }
+
+    fn create_loan(&self, text: String, borrowed: VarId, is_mut: bool, range: TextRange) -> LoanId {
+        let mut loans = self.loans.borrow_mut();
+        let id = LoanId(loans.len());
+        debug!("Created loan '{}' {:?}", text, id);
+        loans.push(Loan {
+            id,
+            text,
+            borrowed,
+            is_mut,
+            created_at: range,
+            live_range: range,
+            invalidated_at: None,
+        });
+        id
+    }
+
+    /// `var` has been written. If it is the value behind a mutable reference (`*r = value`), the value is stored in
+    /// the place the reference borrows: `&mut T` is invariant in `T`, so the place can't outlive the value's borrows.
+    pub fn write_through(&self, var: VarId, range: TextRange) -> CheckerResult {
+        let (parent, borrows, loans) = {
+            let var = self.resolve_var(var).borrow();
+            (var.parent, var.borrows.clone(), var.loans.clone())
+        };
+        let parent = match parent {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let places = self
+            .resolve_var(parent)
+            .borrow()
+            .borrows
+            .iter()
+            .filter_map(|borrow| match borrow {
+                Borrow::Mutable(place) => Some(*place),
+                Borrow::Immutable(_) => None,
+            })
+            .collect::<Vec<_>>();
+        for place in places {
+            self.store_borrows(&borrows, &loans, place, range)?;
+        }
+        Ok(())
+    }
+
+    /// Makes `place` hold `borrows` in addition to its own, because a value holding them has been stored in it at
+    /// `range` (e.g. by a callee that is invariant in the lifetime of the place)
+    pub fn store_borrows(
+        &self,
+        borrows: &[Borrow],
+        loans: &[LoanId],
+        place: VarId,
+        range: TextRange,
+    ) -> CheckerResult {
+        self.resolve_var(place)
+            .borrow_mut()
+            .hold_borrows(borrows, loans, range, self)?;
+        self.write_through(place, range)
+    }
+
+    /// The borrows and loans of `var`
+    pub fn borrows(&self, var: VarId) -> (Vec<Borrow>, Vec<LoanId>) {
+        let var = self.resolve_var(var).borrow();
+        (var.borrows.clone(), var.loans.clone())
+    }
+
+    fn loan_borrowed(&self, loan: LoanId) -> VarId {
+        self.loans.borrow()[loan.0].borrowed
+    }
+
+    /// Where the loan of `borrowed` that `var` holds has been created, if it holds one
+    pub fn loan_created_at(&self, var: VarId, borrowed: VarId) -> Option<TextRange> {
+        let loans = self.loans.borrow();
+        self.resolve_var(var)
+            .borrow()
+            .loans
+            .iter()
+            .map(|loan| &loans[loan.0])
+            .find(|loan| loan.borrowed == borrowed)
+            .map(|loan| loan.created_at)
+    }
+
+    fn extend_loan(&self, loan: LoanId, range: TextRange) {
+        let loan = &mut self.loans.borrow_mut()[loan.0];
+        loan.live_range = loan.live_range.cover(range);
+    }
+
+    /// Invalidates the loans of `borrowed` that conflict with an access at `range`. A shared borrow only
+    /// conflicts with mutable loans, `except` is the loan created by the access (if any).
+    fn invalidate_loans(
+        &self,
+        borrowed: VarId,
+        except: Option<LoanId>,
+        mut_only: bool,
+        range: TextRange,
+    ) {
+        for loan in self.loans.borrow_mut().iter_mut() {
+            if loan.borrowed == borrowed
+                && Some(loan.id) != except
+                && (loan.is_mut || !mut_only)
+                && loan.invalidated_at.is_none()
+            {
+                loan.invalidated_at = Some(range);
+            }
+        }
+    }
+
     pub fn resolve_var(&self, id: VarId) -> &RefCell<Var> {
         &self.vars[id.0]
     }
@@ -142,24 +372,141 @@ impl Vars {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Var {
     status: VarStatus,
-    valid: bool,
+    invalidation: Option<Invalidation>,
     identifier: String,
     is_mut: bool,
     is_copy: bool,
     id: VarId,
     deref_var: Option<VarId>,
     parent: Option<VarId>,
+    /// The vars of the places inside self that have been borrowed on their own, by name (see `Vars::get_field_var`)
+    fields: Vec<(String, VarId)>,
+    /// For the var of a place inside another var, that var
+    base: Option<VarId>,
     borrows: Vec<Borrow>,
+    /// The lifetime parameters of self's type that hold its borrows, e.g. `'a` for the borrow of `x` held by
+    /// `Wrapper { r: &x }`. Borrows without an entry are held through every lifetime parameter.
+    through: Vec<(VarId, String)>,
+    /// The borrows that have been stored in self since its initialization (see `Vars::store_borrows`), with where
+    /// they have been stored
+    stored_at: Vec<(VarId, TextRange)>,
+    /// The lifetime parameters of the signature whose data self holds (e.g. by holding a parameter of type `&'a T`),
+    /// each with the parameter that holds it
+    carries: Vec<(OriginId, String)>,
     origin: OriginId,
+    initialized_at: Option<TextRange>,
+    live_range: Option<TextRange>,
+    kind: VarKind,
+    loans: Vec<LoanId>,
+}
+
+/// The vars and loans at a certain point of the analysis, to go back to it
+pub struct VarsState {
+    vars: Vec<Var>,
+    loans: Vec<Loan>,
+}
+
+/// The state of a var at a certain point of the analysis
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarSnapshot {
+    pub id: VarId,
+    pub identifier: String,
+    pub kind: VarKind,
+    pub status: VarStatus,
+    pub borrows: Vec<Borrow>,
+    pub invalidation: Option<Invalidation>,
+    pub origin: OriginId,
+    /// For deref vars (e.g. `*r`), the var that is dereferenced
+    pub parent: Option<VarId>,
+    pub initialized_at: Option<TextRange>,
+    /// Covers every initialization and use of the var
+    pub live_range: Option<TextRange>,
+}
+
+impl VarSnapshot {
+    pub fn is_valid(&self) -> bool {
+        self.invalidation.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LoanId(usize);
+
+/// A borrow created by a reference expression (e.g. `&mut x`). It is live from its creation until the
+/// last use of a var holding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loan {
+    pub id: LoanId,
+    pub text: String,
+    pub borrowed: VarId,
+    pub is_mut: bool,
+    /// The reference expression that created the loan
+    pub created_at: TextRange,
+    pub live_range: TextRange,
+    /// The first conflicting access to the borrowed var (another borrow, an assignment or a move)
+    pub invalidated_at: Option<TextRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarKind {
+    Local,
+    Param,
+    Temporary,
+    Literal,
+    /// A `static` or `const` item
+    Static,
+    Deref,
+    /// A place inside a local (e.g. `x.a`), see `Vars::get_field_var`
+    Field,
 }
 
 impl Var {
+    /// An uninitialized, immutable and non-`Copy` var that doesn't hold any borrows yet
+    fn new(kind: VarKind, identifier: String, origin: OriginId) -> Self {
+        Self {
+            status: VarStatus::Unitialized,
+            invalidation: None,
+            identifier,
+            is_mut: false,
+            is_copy: false,
+            id: VarId(0),
+            deref_var: None,
+            parent: None,
+            fields: Vec::new(),
+            base: None,
+            borrows: Vec::new(),
+            through: Vec::new(),
+            stored_at: Vec::new(),
+            carries: Vec::new(),
+            origin,
+            initialized_at: None,
+            live_range: None,
+            kind,
+            loans: Vec::new(),
+        }
+    }
+
+    /// Extends the live ranges of self and of the loans it holds to `range`
+    pub fn mark_used(&mut self, range: TextRange, vars: &Vars) {
+        self.live_range = Some(match self.live_range {
+            Some(live_range) => live_range.cover(range),
+            None => range,
+        });
+        for loan in &self.loans {
+            vars.extend_loan(*loan, range);
+        }
+    }
+
     pub fn assert_usable(&self) -> CheckerResult {
-        if !self.valid {
-            Err(CheckerError::Invalid(self.identifier.to_string(), self.id))
+        if let Some(invalidation) = &self.invalidation {
+            Err(CheckerError::Invalid(
+                self.identifier.to_string(),
+                self.id,
+                invalidation.clone(),
+            ))
         } else if self.status == VarStatus::Unitialized {
             Err(CheckerError::Uninitialized(
                 self.identifier.to_string(),
@@ -172,6 +519,20 @@ impl Var {
         }
     }
 
+    /// A read conflicts with a mutable borrow of self but not with shared ones
+    pub fn read(&mut self, range: TextRange, vars: &Vars) {
+        if let VarStatus::MutBorrowed(_) = self.status {
+            self.invalidate_borrowers(InvalidationCause::Used, range, vars);
+            vars.invalidate_loans(self.id, None, true, range);
+            self.status = VarStatus::Initialized;
+        }
+        self.invalidate_fields(
+            &|status| matches!(status, VarStatus::MutBorrowed(_)).then(|| InvalidationCause::Used),
+            range,
+            vars,
+        );
+    }
+
     fn assert_assignable(&self) -> CheckerResult {
         if self.status != VarStatus::Unitialized && !self.is_mut {
             Err(CheckerError::ImmutableAssigned(
@@ -183,23 +544,113 @@ impl Var {
         }
     }
 
-    fn invalidate_borrowers(&self, vars: &Vars) {
+    fn invalidate_borrowers(&self, cause: InvalidationCause, range: TextRange, vars: &Vars) {
+        let invalidation = Invalidation {
+            cause,
+            target: self.identifier.clone(),
+            range,
+            chain: Vec::new(),
+        };
+        self.propagate_invalidation(&invalidation, vars);
+    }
+
+    fn propagate_invalidation(&self, invalidation: &Invalidation, vars: &Vars) {
         match &self.status {
             VarStatus::Borrowed(borrowers) => borrowers
                 .iter()
-                .for_each(|borrower| self.invalidate_var(*borrower, vars)),
-            VarStatus::MutBorrowed(borrower) => self.invalidate_var(*borrower, vars),
+                .for_each(|borrower| self.invalidate_var(*borrower, invalidation, vars)),
+            VarStatus::MutBorrowed(borrower) => self.invalidate_var(*borrower, invalidation, vars),
+            VarStatus::Reserved(borrower, readers) => std::iter::once(borrower)
+                .chain(readers)
+                .for_each(|borrower| self.invalidate_var(*borrower, invalidation, vars)),
             _ => {}
         }
     }
 
+    /// Invalidates the borrowers of the places inside self (see `Vars::get_field_var`) whose borrows conflict with an
+    /// access to all of self. `conflict` returns the cause of the invalidation for the status of a place, if the
+    /// access conflicts with it.
+    fn invalidate_fields(&self, conflict: &Conflict, range: TextRange, vars: &Vars) {
+        for (_, field) in &self.fields {
+            let mut field = vars.resolve_var(*field).borrow_mut();
+            field.invalidate_on_conflict(conflict, range, vars);
+            field.invalidate_fields(conflict, range, vars);
+        }
+    }
+
+    /// Like `invalidate_fields` for the places self is inside of: borrowing a place conflicts with the borrows of the
+    /// places containing it
+    fn invalidate_bases(
+        &self,
+        conflict: &Conflict,
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
+        let mut base = self.base;
+        while let Some(id) = base {
+            let mut var = vars.resolve_var(id).borrow_mut();
+            var.assert_usable()?;
+            var.invalidate_on_conflict(conflict, range, vars);
+            base = var.base;
+        }
+        Ok(())
+    }
+
+    fn invalidate_on_conflict(&mut self, conflict: &Conflict, range: TextRange, vars: &Vars) {
+        if let Some(cause) = conflict(&self.status) {
+            self.invalidate_borrowers(cause, range, vars);
+            vars.invalidate_loans(self.id, None, false, range);
+            self.status = VarStatus::Initialized;
+        }
+    }
+
+    /// The name of self in diagnostics and traces
+    pub fn rename(&mut self, identifier: String) {
+        self.identifier = identifier;
+    }
+
+    /// Records that self holds data of the lifetime parameters `origins`
+    pub fn carry(&mut self, origins: Vec<OriginId>) {
+        for origin in origins {
+            self.carries.push((origin, self.identifier.clone()));
+        }
+    }
+
+    /// The lifetime parameters whose data self holds, directly or through the references it borrows
+    pub fn carried_origins(&self, vars: &Vars) -> Vec<(OriginId, String)> {
+        let mut carried = self.carries.clone();
+        for borrow in &self.borrows {
+            let borrowed = vars.resolve_var(borrow.target()).borrow();
+            if let Some(parent) = borrowed.parent {
+                // The value behind a reference holds what the reference holds
+                carried.extend(vars.resolve_var(parent).borrow().carried_origins(vars));
+            }
+        }
+        carried
+    }
+
+    /// Makes sure that every var borrowed by self lives at least as long as `origin`
     pub fn validate_for_origin(
         &self,
         origin: OriginId,
         origins: &Origins,
         vars: &Vars,
     ) -> CheckerResult {
-        // TODO
+        for borrow in &self.borrows {
+            let borrowed = vars.resolve_var(borrow.target()).borrow();
+            if let Some(parent) = borrowed.parent {
+                // The value behind a reference lives wherever the reference points to
+                vars.resolve_var(parent)
+                    .borrow()
+                    .validate_for_origin(origin, origins, vars)?;
+            } else if borrowed.origin != origin && !origin.has_parent(borrowed.origin, origins) {
+                return Err(CheckerError::InvalidOrigin(
+                    borrowed.identifier.clone(),
+                    borrowed.id,
+                    origin,
+                ));
+            }
+        }
         Ok(())
     }
 
@@ -214,43 +665,237 @@ impl Var {
     pub fn initialize_with_values(
         &mut self,
         value_sources: Vec<VarId>,
+        range: TextRange,
         vars: &Vars,
     ) -> CheckerResult {
         debug!("Initializing {} from {:?}", self.id, value_sources);
 
+        self.reset(range, vars)?;
+
+        // A borrow is only held through the lifetimes it is tagged with if every source holding it tags it
+        let mut untagged = Vec::new();
+        for value_source in value_sources {
+            let moved = vars
+                .resolve_var(value_source)
+                .borrow_mut()
+                .transition_moved(range, vars)?;
+            untagged.extend(
+                moved
+                    .borrows
+                    .iter()
+                    .map(Borrow::target)
+                    .filter(|target| !moved.through.iter().any(|(tagged, _)| tagged == target)),
+            );
+            self.borrows.extend(moved.borrows);
+            self.through.extend(moved.through);
+            self.carries.extend(moved.carries);
+            self.loans.extend(moved.loans);
+        }
+        self.through
+            .retain(|(target, _)| !untagged.contains(target));
+        self.mark_used(range, vars);
+
+        self.register_borrows(range, vars)
+    }
+
+    /*
+        Like initialize_with_values, with the fields of a struct or enum variant as the value sources. Each field
+        comes with the lifetime parameters of its type, through which self holds the field's borrows. If they are
+        unknown (e.g. the field's type is a type parameter), self holds them through every lifetime parameter.
+    */
+    pub fn initialize_with_fields(
+        &mut self,
+        fields: Vec<(VarId, Option<Vec<String>>)>,
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
+        let targets = fields
+            .iter()
+            .map(|(field, _)| {
+                vars.resolve_var(*field)
+                    .borrow()
+                    .borrows
+                    .iter()
+                    .map(Borrow::target)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        self.initialize_with_values(
+            fields.iter().map(|(field, _)| *field).collect(),
+            range,
+            vars,
+        )?;
+
+        let mut through = Vec::new();
+        let mut untagged = Vec::new();
+        for ((_, lifetimes), targets) in fields.into_iter().zip(targets) {
+            match lifetimes {
+                Some(lifetimes) if !lifetimes.is_empty() => {
+                    for target in targets {
+                        through.extend(lifetimes.iter().map(|lifetime| (target, lifetime.clone())));
+                    }
+                }
+                _ => untagged.extend(targets),
+            }
+        }
+        through.retain(|(target, _)| !untagged.contains(target));
+        through.sort();
+        through.dedup();
+        self.through = through;
+
+        Ok(())
+    }
+
+    /*
+        1. Make sure self can be assigned to (i.e. it is either uninitialized or mutable)
+        2. Because this value will be dropped, all borrowers are invalidated
+        3. The status of self is updated
+        4. self takes the borrows that source holds through the given lifetime parameters (or all of them if
+           there are none), i.e. the borrows of a field whose type mentions these lifetimes
+    */
+    pub fn initialize_with_projection(
+        &mut self,
+        source: VarId,
+        lifetimes: Option<Vec<String>>,
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
+        debug!(
+            "Initializing {} as a projection of {} through {:?}",
+            self.id, source, lifetimes
+        );
+
+        self.reset(range, vars)?;
+
+        let source = vars.resolve_var(source).borrow();
+        source.assert_usable()?;
+        let is_projected = |target: VarId| match &lifetimes {
+            Some(lifetimes) => {
+                let mut tags = source
+                    .through
+                    .iter()
+                    .filter(|(tagged, _)| *tagged == target)
+                    .peekable();
+                tags.peek().is_none() || tags.any(|(_, lifetime)| lifetimes.contains(lifetime))
+            }
+            None => true,
+        };
+        self.borrows = source
+            .borrows
+            .iter()
+            .filter(|borrow| is_projected(borrow.target()))
+            .cloned()
+            .collect();
+        self.loans = source
+            .loans
+            .iter()
+            .filter(|loan| is_projected(vars.loan_borrowed(**loan)))
+            .copied()
+            .collect();
+        self.carries = source.carries.clone();
+        drop(source);
+        self.mark_used(range, vars);
+
+        self.register_borrows(range, vars)
+    }
+
+    /// Adds `borrows` to the borrows of self without assigning self, see `Vars::store_borrows`
+    fn hold_borrows(
+        &mut self,
+        borrows: &[Borrow],
+        loans: &[LoanId],
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
+        for borrow in borrows {
+            if borrow.target() == self.id || self.borrows.contains(borrow) {
+                continue;
+            }
+            trace!("{} got {:?} stored", self.id, borrow);
+
+            self.borrows.push(borrow.clone());
+            self.through
+                .retain(|(target, _)| *target != borrow.target());
+            self.stored_at.push((borrow.target(), range));
+            match borrow {
+                Borrow::Mutable(target) => vars
+                    .resolve_var(*target)
+                    .borrow_mut()
+                    .transition_mut_borrowed(self.id, range, vars)?,
+                Borrow::Immutable(target) => vars
+                    .resolve_var(*target)
+                    .borrow_mut()
+                    .transition_borrowed(self.id, range, vars)?,
+            }
+        }
+        for loan in loans {
+            if !self.loans.contains(loan) {
+                self.loans.push(*loan);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepares self to be assigned a new value: checks that self is assignable, invalidates the borrowers of its
+    /// old value and forgets the borrows it held
+    fn reset(&mut self, range: TextRange, vars: &Vars) -> CheckerResult {
         self.assert_assignable()?;
 
-        self.invalidate_borrowers(vars);
+        self.invalidate_borrowers(InvalidationCause::Assigned, range, vars);
+        vars.invalidate_loans(self.id, None, false, range);
+        self.invalidate_fields(
+            &|status| is_borrowed(status).then(|| InvalidationCause::Assigned),
+            range,
+            vars,
+        );
 
         self.status = VarStatus::Initialized;
-        self.valid = true;
+        self.invalidation = None;
+        self.initialized_at = Some(range);
 
         self.borrows = Vec::new();
+        self.through = Vec::new();
+        self.stored_at = Vec::new();
+        self.carries = Vec::new();
+        self.loans = Vec::new();
 
-        for value_source in value_sources {
-            self.borrows.extend(
-                vars.resolve_var(value_source)
-                    .borrow_mut()
-                    .transition_moved(vars)?,
-            );
-        }
+        Ok(())
+    }
+
+    /// Prepares self to get one of its fields assigned: invalidates the borrowers of self like an assignment to all of
+    /// it would, but keeps the borrows held by the other fields
+    pub fn reset_field(&mut self, range: TextRange, vars: &Vars) -> CheckerResult {
+        self.assert_usable()?;
+
+        self.invalidate_borrowers(InvalidationCause::Assigned, range, vars);
+        vars.invalidate_loans(self.id, None, false, range);
+        self.invalidate_fields(
+            &|status| is_borrowed(status).then(|| InvalidationCause::Assigned),
+            range,
+            vars,
+        );
+        self.status = VarStatus::Initialized;
 
-        // Notify all variables that are now borrowed by us of their new borrower
+        Ok(())
+    }
+
+    /// Notifies all vars that are borrowed by self of their new borrower
+    fn register_borrows(&self, range: TextRange, vars: &Vars) -> CheckerResult {
         for borrow in &self.borrows {
             match borrow {
                 Borrow::Mutable(borrow) => {
                     vars.resolve_var(*borrow)
                         .borrow_mut()
-                        .transition_mut_borrowed(self.id, vars)?;
+                        .transition_mut_borrowed(self.id, range, vars)?;
                 }
                 Borrow::Immutable(borrow) => {
                     vars.resolve_var(*borrow)
                         .borrow_mut()
-                        .transition_borrowed(self.id, vars)?;
+                        .transition_borrowed(self.id, range, vars)?;
                 }
             }
         }
-
         Ok(())
     }
 
@@ -264,36 +909,164 @@ impl Var {
         &mut self,
         is_mut: bool,
         borrowed_vars: Vec<VarId>,
+        range: TextRange,
         vars: &Vars,
     ) -> CheckerResult {
         debug!("Initializing {} as a borrow from {:?}", self.id, borrowed_vars);
 
-        self.assert_assignable()?;
-
-        self.invalidate_borrowers(vars);
-
-        self.status = VarStatus::Initialized;
-        self.valid = true;
+        self.reset(range, vars)?;
 
         self.borrows = Vec::with_capacity(borrowed_vars.len());
+        self.loans = Vec::with_capacity(borrowed_vars.len());
         for borrowed_var in borrowed_vars {
+            let text = format!(
+                "&{}{}",
+                if is_mut { "mut " } else { "" },
+                vars.resolve_var(borrowed_var).borrow().identifier
+            );
+            let loan = vars.create_loan(text, borrowed_var, is_mut, range);
+            if is_mut {
+                vars.invalidate_loans(borrowed_var, Some(loan), false, range);
+            } else {
+                // A shared borrow only conflicts with a reservation once the reservation is activated
+                let reservation = match &vars.resolve_var(borrowed_var).borrow().status {
+                    VarStatus::Reserved(reserved_by, _) if *reserved_by != self.id => vars
+                        .resolve_var(*reserved_by)
+                        .borrow()
+                        .loans
+                        .first()
+                        .copied(),
+                    _ => None,
+                };
+                vars.invalidate_loans(borrowed_var, reservation, true, range);
+            }
+            self.loans.push(loan);
+
             if is_mut {
                 self.borrows.push(Borrow::Mutable(borrowed_var));
                 vars.resolve_var(borrowed_var)
                     .borrow_mut()
-                    .transition_mut_borrowed(self.id, vars)?;
+                    .transition_mut_borrowed(self.id, range, vars)?;
             } else {
                 self.borrows.push(Borrow::Immutable(borrowed_var));
                 vars.resolve_var(borrowed_var)
                     .borrow_mut()
-                    .transition_borrowed(self.id, vars)?;
+                    .transition_borrowed(self.id, range, vars)?;
             }
         }
+        self.mark_used(range, vars);
+
+        Ok(())
+    }
+
+    /*
+        Like initialize_with_borrows with a single mutable borrow, except that the borrow is only reserved:
+        until it is activated, the borrowed var can still be read and borrowed immutably
+    */
+    pub fn initialize_with_reservation(
+        &mut self,
+        borrowed_var: VarId,
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
+        debug!(
+            "Initializing {} as a reservation of {}",
+            self.id, borrowed_var
+        );
+
+        self.reset(range, vars)?;
+
+        let text = format!(
+            "&mut {}",
+            vars.resolve_var(borrowed_var).borrow().identifier
+        );
+        let loan = vars.create_loan(text, borrowed_var, true, range);
+        // Until it is activated, the reservation only conflicts like a shared borrow
+        vars.invalidate_loans(borrowed_var, Some(loan), true, range);
+        self.loans = vec![loan];
+        self.borrows = vec![Borrow::Mutable(borrowed_var)];
+        vars.resolve_var(borrowed_var)
+            .borrow_mut()
+            .transition_reserved(self.id, range, vars)?;
+        self.mark_used(range, vars);
 
         Ok(())
     }
 
-    fn transition_mut_borrowed(&mut self, borrower: VarId, vars: &Vars) -> CheckerResult {
+    /// Activates the reservations self holds, see `initialize_with_reservation`
+    pub fn activate_reservations(&self, range: TextRange, vars: &Vars) {
+        for borrow in &self.borrows {
+            if let Borrow::Mutable(borrowed) = borrow {
+                vars.resolve_var(*borrowed)
+                    .borrow_mut()
+                    .activate_reservation(self.id, self.loans.first().copied(), range, vars);
+            }
+        }
+    }
+
+    /// Turns the reservation of `borrower` into a mutable borrow, which conflicts with the shared borrows created
+    /// since the reservation. Does nothing if the reservation has been invalidated in the meantime.
+    fn activate_reservation(
+        &mut self,
+        borrower: VarId,
+        loan: Option<LoanId>,
+        range: TextRange,
+        vars: &Vars,
+    ) {
+        let readers = match &self.status {
+            VarStatus::Reserved(reserved_by, readers) if *reserved_by == borrower => {
+                readers.clone()
+            }
+            _ => return,
+        };
+        trace!("{} got its reservation by {} activated", self.id, borrower);
+
+        self.status = VarStatus::Borrowed(readers);
+        self.invalidate_borrowers(InvalidationCause::MutBorrowedWhileShared, range, vars);
+        vars.invalidate_loans(self.id, loan, false, range);
+        self.status = VarStatus::MutBorrowed(borrower);
+    }
+
+    fn transition_reserved(
+        &mut self,
+        borrower: VarId,
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
+        trace!("{} got reserved by {}", self.id, borrower);
+
+        if !self.is_mut {
+            return Err(CheckerError::ImmutableBorrowedMutable(
+                self.identifier.clone(),
+                self.id,
+            ));
+        }
+
+        self.assert_usable()?;
+
+        let readers = match &self.status {
+            VarStatus::Initialized => Vec::new(),
+            // Shared borrows may live on until the activation
+            VarStatus::Borrowed(readers) => readers.clone(),
+            VarStatus::MutBorrowed(_) | VarStatus::Reserved(..) => {
+                self.invalidate_borrowers(InvalidationCause::MutBorrowedTwice, range, vars);
+                Vec::new()
+            }
+            VarStatus::Unitialized | VarStatus::Moved => unreachable!(), // Already covered by self.assert_usable()
+        };
+        self.status = VarStatus::Reserved(borrower, readers);
+        self.invalidate_fields(&|status| borrow_conflict(status, false), range, vars);
+        self.invalidate_bases(&|status| borrow_conflict(status, false), range, vars)?;
+
+        Ok(())
+    }
+
+    fn transition_mut_borrowed(
+        &mut self,
+        borrower: VarId,
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
         trace!("{} got borrowed mutably by {}", self.id, borrower);
 
         if !self.is_mut {
@@ -307,99 +1080,144 @@ impl Var {
 
         match &self.status {
             VarStatus::Initialized => {}
-            VarStatus::Borrowed(borrowers) => borrowers
-                .iter()
-                .for_each(|borrower| self.invalidate_var(*borrower, vars)),
-            VarStatus::MutBorrowed(borrower) => self.invalidate_var(*borrower, vars),
+            VarStatus::Borrowed(_) => {
+                self.invalidate_borrowers(InvalidationCause::MutBorrowedWhileShared, range, vars)
+            }
+            VarStatus::MutBorrowed(_) | VarStatus::Reserved(..) => {
+                self.invalidate_borrowers(InvalidationCause::MutBorrowedTwice, range, vars)
+            }
             VarStatus::Unitialized | VarStatus::Moved => unreachable!(), // Already covered by self.assert_usable()
         }
         self.status = VarStatus::MutBorrowed(borrower);
+        self.invalidate_fields(&|status| borrow_conflict(status, true), range, vars);
+        self.invalidate_bases(&|status| borrow_conflict(status, true), range, vars)?;
 
         Ok(())
     }
 
-    fn transition_borrowed(&mut self, borrower: VarId, vars: &Vars) -> CheckerResult {
+    fn transition_borrowed(
+        &mut self,
+        borrower: VarId,
+        range: TextRange,
+        vars: &Vars,
+    ) -> CheckerResult {
         trace!("{} got borrowed by {}", self.id, borrower);
 
         self.assert_usable()?;
 
         if let VarStatus::Initialized = &self.status {
             self.status = VarStatus::Borrowed(vec![borrower]);
-        } else if let VarStatus::Borrowed(borrowers) = &mut self.status {
+        } else if let VarStatus::Borrowed(borrowers) | VarStatus::Reserved(_, borrowers) =
+            &mut self.status
+        {
             borrowers.push(borrower)
-        } else if let VarStatus::MutBorrowed(prev_borrower) = self.status {
-            self.invalidate_var(prev_borrower, vars);
+        } else if let VarStatus::MutBorrowed(_) = self.status {
+            self.invalidate_borrowers(InvalidationCause::SharedBorrowedWhileMut, range, vars);
             self.status = VarStatus::Borrowed(vec![borrower]);
         } else {
             unreachable!(); // Already covered by self.assert_usable()
         }
+        self.invalidate_fields(&|status| borrow_conflict(status, false), range, vars);
+        self.invalidate_bases(&|status| borrow_conflict(status, false), range, vars)?;
 
         Ok(())
     }
 
-    // Returns the status of self to replicate it in the var that received the move
-    fn transition_moved(&mut self, vars: &Vars) -> Result<Vec<Borrow>, CheckerError> {
+    // Returns the borrows of self to replicate them in the var that received the move
+    fn transition_moved(
+        &mut self,
+        range: TextRange,
+        vars: &Vars,
+    ) -> Result<MovedValue, CheckerError> {
         trace!("{} got moved", self.id);
 
         self.assert_usable()?;
 
         if self.is_copy {
-            return Ok(self.borrows.clone());
+            return Ok(MovedValue {
+                borrows: self.borrows.clone(),
+                through: self.through.clone(),
+                carries: self.carries.clone(),
+                loans: self.loans.clone(),
+            });
+        }
+
+        if self.kind == VarKind::Deref {
+            return Err(CheckerError::MovedOutOfBorrow(
+                self.identifier.to_string(),
+                self.id,
+            ));
         }
 
         match &self.status {
             VarStatus::Initialized => {}
-            VarStatus::Borrowed(borrowers) => borrowers
-                .iter()
-                .for_each(|borrower| self.invalidate_var(*borrower, vars)),
-            VarStatus::MutBorrowed(borrower) => self.invalidate_var(*borrower, vars),
+            VarStatus::Borrowed(_) | VarStatus::MutBorrowed(_) | VarStatus::Reserved(..) => {
+                self.invalidate_borrowers(InvalidationCause::Moved, range, vars)
+            }
             VarStatus::Unitialized | VarStatus::Moved => unreachable!(), // Already covered by self.assert_usable()
         }
 
         self.status = VarStatus::Moved;
-
-        Ok(std::mem::replace(&mut self.borrows, Vec::new()))
+        vars.invalidate_loans(self.id, None, false, range);
+        self.invalidate_fields(
+            &|status| is_borrowed(status).then(|| InvalidationCause::Moved),
+            range,
+            vars,
+        );
+
+        Ok(MovedValue {
+            borrows: std::mem::replace(&mut self.borrows, Vec::new()),
+            through: std::mem::replace(&mut self.through, Vec::new()),
+            carries: std::mem::replace(&mut self.carries, Vec::new()),
+            loans: std::mem::replace(&mut self.loans, Vec::new()),
+        })
     }
 
-    fn invalidate_var(&self, var: VarId, vars: &Vars) {
-        vars.resolve_var(var).borrow_mut().invalidate(self.id, vars);
+    fn invalidate_var(&self, var: VarId, invalidation: &Invalidation, vars: &Vars) {
+        vars.resolve_var(var)
+            .borrow_mut()
+            .invalidate(self, invalidation, vars);
     }
 
-    fn invalidate(&mut self, invalidated_by: VarId, vars: &Vars) {
-        let mut invalidator_found = false;
-        for borrow in &self.borrows {
-            match borrow {
-                Borrow::Mutable(borrow) | Borrow::Immutable(borrow) => {
-                    if *borrow == invalidated_by {
-                        invalidator_found = true;
-                    }
-                }
-            }
-        }
-        if !invalidator_found {
-            return;
-        }
-        trace!("{} got invalidated by {}", self.id, invalidated_by);
+    fn invalidate(&mut self, invalidated_by: &Var, invalidation: &Invalidation, vars: &Vars) {
+        let borrow = match self
+            .borrows
+            .iter()
+            .find(|borrow| borrow.target() == invalidated_by.id)
+        {
+            Some(borrow) => borrow,
+            None => return,
+        };
+        trace!("{} got invalidated by {}", self.id, invalidated_by.id);
+
+        let mut invalidation = invalidation.clone();
+        invalidation.chain.push(BorrowStep {
+            borrower: self.identifier.clone(),
+            borrowed: invalidated_by.identifier.clone(),
+            is_mut: matches!(borrow, Borrow::Mutable(_)),
+            range: self
+                .stored_at
+                .iter()
+                .find(|(stored, _)| *stored == invalidated_by.id)
+                .map(|(_, range)| *range)
+                .or(self.initialized_at),
+        });
 
-        self.valid = false;
+        // Keep the first cause, it is the one that explains why the var is invalid
+        self.invalidation
+            .get_or_insert_with(|| invalidation.clone());
 
-        match &self.status {
-            VarStatus::Unitialized | VarStatus::Initialized | VarStatus::Moved => {}
-            VarStatus::Borrowed(borrowers) => borrowers
-                .iter()
-                .for_each(|borrower| self.invalidate_var(*borrower, vars)),
-            VarStatus::MutBorrowed(borrower) => self.invalidate_var(*borrower, vars),
-        }
+        self.propagate_invalidation(&invalidation, vars);
 
         if let Some(parent) = self.parent {
-            self.invalidate_var(parent, vars); // TODO
+            self.invalidate_var(parent, &invalidation, vars); // TODO
         }
     }
 }
 
 impl Display for Var {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if !self.valid {
+        if self.invalidation.is_some() {
             f.write_str(&format!(
                 "Local '{}' ${} (invalid)",
                 self.identifier, self.id.0
@@ -417,54 +1235,184 @@ impl Display for Var {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum VarStatus {
+/// The cause of invalidating the borrowers of a place with the given status because of an access to an overlapping
+/// place, if they conflict
+type Conflict = dyn Fn(&VarStatus) -> Option<InvalidationCause>;
+
+fn is_borrowed(status: &VarStatus) -> bool {
+    matches!(
+        status,
+        VarStatus::Borrowed(_) | VarStatus::MutBorrowed(_) | VarStatus::Reserved(..)
+    )
+}
+
+/// The conflict of a borrow (`is_mut`) of an overlapping place with a place that has the status `status`
+fn borrow_conflict(status: &VarStatus, is_mut: bool) -> Option<InvalidationCause> {
+    match status {
+        VarStatus::MutBorrowed(_) | VarStatus::Reserved(..) if is_mut => {
+            Some(InvalidationCause::MutBorrowedTwice)
+        }
+        VarStatus::Borrowed(_) if is_mut => Some(InvalidationCause::MutBorrowedWhileShared),
+        VarStatus::MutBorrowed(_) => Some(InvalidationCause::SharedBorrowedWhileMut),
+        _ => None,
+    }
+}
+
+/// What a moved var hands over to the var receiving its value
+struct MovedValue {
+    borrows: Vec<Borrow>,
+    through: Vec<(VarId, String)>,
+    carries: Vec<(OriginId, String)>,
+    loans: Vec<LoanId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarStatus {
     Borrowed(Vec<VarId>),
     MutBorrowed(VarId),
+    /// Reserved for a two-phase mutable borrow (e.g. the `&mut v` of `v.push(v.len())`) that hasn't been activated
+    /// yet. Until then, the var can still be read and borrowed immutably, by the vars in the second field.
+    Reserved(VarId, Vec<VarId>),
     Unitialized,
     Initialized,
     Moved,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum Borrow {
+pub enum Borrow {
     Mutable(VarId),
     Immutable(VarId),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl Borrow {
+    pub fn target(&self) -> VarId {
+        match self {
+            Borrow::Mutable(target) | Borrow::Immutable(target) => *target,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct OriginId(usize);
 
+/// What an origin stands for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OriginKind {
+    /// Outlives every other origin
+    Static,
+    /// A lifetime parameter of the function's signature (e.g. `'a`)
+    Signature(String),
+    /// The scope of the function's parameters
+    Parameters,
+    /// A block, including the function's body
+    Block,
+}
+
+struct Origin {
+    parent: Option<OriginId>,
+    kind: OriginKind,
+    /// The block or the declaration of the lifetime parameter
+    range: Option<TextRange>,
+}
+
 pub struct Origins {
-    parent_origins: Vec<Option<OriginId>>,
+    origins: Vec<Origin>,
+    /// `(longer, shorter)`: `longer` outlives `shorter`, in addition to the outlives relation given by the parents
+    outlives: Vec<(OriginId, OriginId)>,
 }
 
 impl Origins {
     pub fn new() -> Self {
         Self {
-            parent_origins: Vec::new(),
+            origins: Vec::new(),
+            outlives: Vec::new(),
         }
     }
 
-    pub fn create_unbound_origin(&mut self) -> OriginId {
-        let id = self.parent_origins.len();
-        self.parent_origins.push(None);
-        OriginId(id)
+    pub fn create_unbound_origin(
+        &mut self,
+        kind: OriginKind,
+        range: Option<TextRange>,
+    ) -> OriginId {
+        self.add_origin(None, kind, range)
     }
 
-    pub fn create_bound_origin(&mut self, parent: OriginId) -> OriginId {
-        let id = self.parent_origins.len();
-        self.parent_origins.push(Some(parent));
-        OriginId(id)
+    pub fn create_bound_origin(
+        &mut self,
+        parent: OriginId,
+        kind: OriginKind,
+        range: Option<TextRange>,
+    ) -> OriginId {
+        self.add_origin(Some(parent), kind, range)
     }
 
     pub fn resolve_parent(&self, origin: OriginId) -> Option<OriginId> {
-        self.parent_origins[origin.0]
+        self.origins[origin.0].parent
+    }
+
+    pub fn kind(&self, origin: OriginId) -> &OriginKind {
+        &self.origins[origin.0].kind
+    }
+
+    pub fn range(&self, origin: OriginId) -> Option<TextRange> {
+        self.origins[origin.0].range
+    }
+
+    /// Records that `longer` outlives `shorter` (e.g. for `'a: 'b`)
+    pub fn add_outlives(&mut self, longer: OriginId, shorter: OriginId) {
+        if !self.outlives.contains(&(longer, shorter)) {
+            self.outlives.push((longer, shorter));
+        }
+    }
+
+    /// The origins `origin` has been declared to outlive with `add_outlives`
+    pub fn outlived_by(&self, origin: OriginId) -> Vec<OriginId> {
+        self.outlives
+            .iter()
+            .filter(|(longer, _)| *longer == origin)
+            .map(|(_, shorter)| *shorter)
+            .collect()
+    }
+
+    /// Whether `longer` outlives `shorter`, by enclosing it or by declared outlives relations
+    pub fn outlives(&self, longer: OriginId, shorter: OriginId) -> bool {
+        let mut pending = vec![longer];
+        let mut visited = Vec::new();
+        while let Some(origin) = pending.pop() {
+            if origin == shorter || shorter.has_parent(origin, self) {
+                return true;
+            }
+            if !visited.contains(&origin) {
+                visited.push(origin);
+                pending.extend(self.outlived_by(origin));
+            }
+        }
+        false
+    }
+
+    /// All origins in the order of their creation
+    pub fn ids(&self) -> impl Iterator<Item = OriginId> {
+        (0..self.origins.len()).map(OriginId)
+    }
+
+    fn add_origin(
+        &mut self,
+        parent: Option<OriginId>,
+        kind: OriginKind,
+        range: Option<TextRange>,
+    ) -> OriginId {
+        let id = OriginId(self.origins.len());
+        self.origins.push(Origin {
+            parent,
+            kind,
+            range,
+        });
+        id
     }
 }
 
 impl OriginId {
-    fn has_parent(self, parent: OriginId, origins: &Origins) -> bool {
+    pub fn has_parent(self, parent: OriginId, origins: &Origins) -> bool {
         let mut origin = self;
         while let Some(p) = origins.resolve_parent(origin) {
             if p == parent {
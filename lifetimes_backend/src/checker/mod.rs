@@ -1,11 +1,20 @@
+mod dot;
+mod error_codes;
 mod implementation;
 
-use std::fmt::{Debug, Display};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+};
 
 use log::debug;
+use syntax::TextRange;
 
-pub use self::implementation::VarId;
-use self::implementation::{OriginId, Origins, Vars};
+pub use self::error_codes::explain;
+pub use self::implementation::{
+    Borrow, Loan, LoanId, OriginId, OriginKind, VarId, VarKind, VarSnapshot, VarStatus,
+};
+use self::implementation::{Origins, Vars, VarsState};
 
 pub type CheckerResult = Result<(), CheckerError>;
 
@@ -13,76 +22,397 @@ pub struct Checker {
     vars: Vars,
     origins: Origins,
     scope: Option<OriginId>,
+    function_scope: Option<OriginId>,
     static_origin: OriginId,
+    /// The vars of the `static` items used so far
+    statics: HashMap<hir::Static, VarId>,
+    /// The temporaries of the statements being checked, innermost last
+    statements: Vec<StatementTemporaries>,
+    trace: Option<Vec<Snapshot>>,
+}
+
+/// Temporaries live in the block of their statement and are dropped at its end, unless they are lifetime-extended
+struct StatementTemporaries {
+    origin: OriginId,
+    vars: Vec<VarId>,
+}
+
+/// The state of all live vars after the statement (or tail expression) at `range`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub range: TextRange,
+    pub vars: Vec<VarSnapshot>,
+}
+
+impl Snapshot {
+    pub fn var(&self, identifier: &str) -> Option<&VarSnapshot> {
+        self.vars
+            .iter()
+            .rev()
+            .find(|var| var.identifier == identifier)
+    }
+
+    /// The borrow graph after the statement as a Graphviz graph: vars are nodes styled by their status, borrows
+    /// are edges and origins are clusters
+    pub fn to_dot(&self) -> String {
+        dot::borrow_graph(&self.vars)
+    }
+}
+
+/// An origin as seen by `Checker::origins`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginInfo {
+    pub id: OriginId,
+    pub kind: OriginKind,
+    /// The enclosing origin, which outlives this one
+    pub parent: Option<OriginId>,
+    /// The origins this one has been declared to outlive, besides its children
+    pub outlives: Vec<OriginId>,
+    /// The block of a scope or the declaration of a lifetime parameter
+    pub range: Option<TextRange>,
+    /// The vars living in the origin (e.g. the locals declared in a block), in the order of creation
+    pub vars: Vec<VarId>,
 }
 
 impl Checker {
     pub fn new() -> Self {
         let mut origins = Origins::new();
-        let static_origin = origins.create_unbound_origin();
+        let static_origin = origins.create_unbound_origin(OriginKind::Static, None);
         Self {
             vars: Vars::new(static_origin),
             origins,
             scope: None,
+            function_scope: None,
             static_origin,
+            statics: HashMap::new(),
+            statements: Vec::new(),
+            trace: None,
+        }
+    }
+
+    /// Makes `snapshot` record the state of the vars, see `take_trace`
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    /// Records the state of all vars living in the current scope or its parents, if tracing is enabled
+    pub fn snapshot(&mut self, range: TextRange) {
+        if self.trace.is_none() {
+            return;
         }
+
+        let scope = self.scope.unwrap();
+        let origins = &self.origins;
+        let vars = self
+            .vars
+            .snapshot(|origin| origin == scope || scope.has_parent(origin, origins));
+        self.trace.as_mut().unwrap().push(Snapshot { range, vars });
+    }
+
+    /// The snapshots recorded so far, in the order of the analysis
+    pub fn take_trace(&mut self) -> Vec<Snapshot> {
+        self.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// The state of every var created so far, including the ones whose scope has been left
+    pub fn all_vars(&self) -> Vec<VarSnapshot> {
+        self.vars.snapshot(|_| true)
+    }
+
+    /// The state of the vars, to go back to it after checking code that doesn't fall through (e.g. the diverging
+    /// `else` block of `let ... else`)
+    pub fn save_state(&self) -> VarsState {
+        self.vars.save()
+    }
+
+    pub fn restore_state(&self, state: VarsState) {
+        self.vars.restore(state);
     }
 
-    pub fn enter_function(&mut self, return_origin: OriginId) {
-        self.scope = Some(return_origin)
+    pub fn loans(&self) -> Vec<Loan> {
+        self.vars.loans()
     }
 
-    /// The initial scope is already entered for you, so only call this when entering an inner scope
-    pub fn enter_scope(&mut self) {
+    /// Enters the scope of the function's parameters, which must outlive the returned value.
+    /// `range` is the function's declaration.
+    pub fn enter_function(&mut self, return_origin: OriginId, range: TextRange) {
+        self.scope = Some(return_origin);
+        self.enter_origin(OriginKind::Parameters, range);
+        self.function_scope = self.scope;
+    }
+
+    /// Leaves the scope of the function's parameters. `return_var` is the value of the function's body
+    pub fn leave_function(&mut self, return_var: Option<VarId>, range: TextRange) -> CheckerResult {
+        self.leave_scope(return_var, range)?;
+        self.function_scope = None;
+        Ok(())
+    }
+
+    /// Checks that `var`, which is returned early by `return`, only borrows vars that outlive the function
+    pub fn check_returned(&self, var: VarId) -> CheckerResult {
+        let function_scope = self.function_scope.unwrap();
+        let return_origin = self
+            .origins
+            .resolve_parent(function_scope)
+            .ok_or(CheckerError::OutermostScopeLeft)?;
+        self.vars
+            .resolve_var(var)
+            .borrow()
+            .validate_for_origin(return_origin, &self.origins, &self.vars)
+            .map_err(|error| match error {
+                CheckerError::InvalidOrigin(identifier, var, _) => {
+                    CheckerError::ReturnsLocal(identifier, var)
+                }
+                error => error,
+            })
+    }
+
+    /// The initial scope is already entered for you, so only call this when entering an inner scope.
+    /// `range` is the block of the scope.
+    pub fn enter_scope(&mut self, range: TextRange) {
+        self.enter_origin(OriginKind::Block, range);
+    }
+
+    fn enter_origin(&mut self, kind: OriginKind, range: TextRange) {
         self.scope = Some(if let Some(parent_scope) = self.scope {
-            self.origins.create_bound_origin(parent_scope)
+            self.origins
+                .create_bound_origin(parent_scope, kind, Some(range))
         } else {
-            self.origins.create_unbound_origin()
+            self.origins.create_unbound_origin(kind, Some(range))
         });
         debug!("Entered scope {}", self.scope.unwrap());
     }
 
-    /// Don't leave the outermost scope
-    pub fn leave_scope(&mut self, return_var: Option<VarId>) -> CheckerResult {
+    /// Creates the origin of a lifetime parameter of the function (e.g. `'a`), which outlives the function's
+    /// parameters. Must be called after `enter_function`.
+    pub fn create_signature_origin(&mut self, name: String, range: TextRange) -> OriginId {
+        let origin = self.origins.create_bound_origin(
+            self.static_origin,
+            OriginKind::Signature(name),
+            Some(range),
+        );
+        self.origins
+            .add_outlives(origin, self.function_scope.unwrap());
+        origin
+    }
+
+    /// Records a declared outlives relation (e.g. `'a: 'b`)
+    pub fn add_outlives(&mut self, longer: OriginId, shorter: OriginId) {
+        self.origins.add_outlives(longer, shorter);
+    }
+
+    /// Every origin created so far with the vars living in it, in the order of creation
+    pub fn origins(&self) -> Vec<OriginInfo> {
+        let vars = self.all_vars();
+        self.origins
+            .ids()
+            .map(|id| OriginInfo {
+                id,
+                kind: self.origins.kind(id).clone(),
+                parent: self.origins.resolve_parent(id),
+                outlives: self.origins.outlived_by(id),
+                range: self.origins.range(id),
+                vars: vars
+                    .iter()
+                    .filter(|var| var.origin == id)
+                    .map(|var| var.id)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Don't leave the outermost scope. `range` is where the scope's vars are dropped
+    pub fn leave_scope(&mut self, return_var: Option<VarId>, range: TextRange) -> CheckerResult {
+        let scope = self.scope.unwrap();
         let parent_scope = self
             .origins
-            .resolve_parent(self.scope.unwrap())
+            .resolve_parent(scope)
             .ok_or(CheckerError::OutermostScopeLeft)?;
 
+        // Values that escape the function's body or its parameter scope are returned to the caller
+        let is_function_return =
+            self.function_scope == Some(scope) || self.function_scope == Some(parent_scope);
+
         if let Some(return_var) = return_var {
             self.vars
                 .resolve_var(return_var)
                 .borrow()
-                .validate_for_origin(parent_scope, &self.origins, &self.vars)?;
+                .validate_for_origin(parent_scope, &self.origins, &self.vars)
+                .map_err(|error| match error {
+                    CheckerError::InvalidOrigin(identifier, var, _) if is_function_return => {
+                        CheckerError::ReturnsLocal(identifier, var)
+                    }
+                    error => error,
+                })?;
         }
 
+        self.vars.drop_origin(scope, return_var, range);
+
         self.scope = Some(parent_scope);
         debug!("Left scope {}", self.scope.unwrap());
         Ok(())
     }
 
+    /// Enters a statement of the current scope, see `leave_statement`
+    pub fn enter_statement(&mut self) {
+        self.statements.push(StatementTemporaries {
+            origin: self.scope.unwrap(),
+            vars: Vec::new(),
+        });
+    }
+
+    /// Drops the temporaries created since the statement has been entered. Temporaries of a block's tail expression
+    /// belong to the statement enclosing the block. `range` is the end of the statement.
+    pub fn leave_statement(&mut self, range: TextRange) {
+        let statement = self.statements.pop().unwrap();
+        self.vars.drop_temporaries(&statement.vars, range);
+    }
+
+    /// Extends the lifetime of a temporary to its block, like `let x = &temporary();` does
+    pub fn extend_temporary(&mut self, var: VarId) {
+        if let Some(statement) = self.statements.last_mut() {
+            statement.vars.retain(|temporary| *temporary != var);
+        }
+    }
+
+    /// Temporaries live in the block of the innermost statement. Outside of statements (i.e. in the tail
+    /// expression of the function's body) they live until the function returns.
+    fn create_temporary(&mut self, create: impl FnOnce(&mut Vars, OriginId) -> VarId) -> VarId {
+        let origin = match self.statements.last() {
+            Some(statement) => statement.origin,
+            None => self.function_scope.or(self.scope).unwrap(),
+        };
+        let var = create(&mut self.vars, origin);
+        if let Some(statement) = self.statements.last_mut() {
+            statement.vars.push(var);
+        }
+        var
+    }
+
     pub fn create_var(&mut self, is_mut: bool, is_copy: bool, identifier: String) -> VarId {
         self.vars
             .create_var(self.scope.unwrap(), is_mut, is_copy, identifier)
     }
 
+    /// Parameters are initialized when the function is entered. `range` is the parameter's declaration
+    pub fn create_param(
+        &mut self,
+        is_mut: bool,
+        is_copy: bool,
+        identifier: String,
+        range: TextRange,
+    ) -> VarId {
+        self.vars
+            .create_param(self.scope.unwrap(), is_mut, is_copy, identifier, range)
+    }
+
+    /// Changes the name `var` has in diagnostics and traces, e.g. to tell it apart from a local shadowing it
+    pub fn rename_var(&self, var: VarId, identifier: String) {
+        self.vars.resolve_var(var).borrow_mut().rename(identifier);
+    }
+
+    /// For the values of expressions like `if`, which are not stored in a local
+    pub fn create_expr_tmp(&mut self, identifier: String) -> VarId {
+        self.create_temporary(|vars, origin| vars.create_expr_tmp(origin, identifier))
+    }
+
     pub fn create_ref_tmp(&mut self, is_mut: bool, text: String) -> VarId {
-        self.vars.create_tmp(self.scope.unwrap(), is_mut, text)
+        self.create_temporary(|vars, origin| vars.create_tmp(origin, is_mut, text))
     }
 
+    /// Literals are promoted to constants, so borrows of them are `'static`
     pub fn create_literal(&mut self, literal: String) -> VarId {
-        self.vars.create_literal(self.scope.unwrap(), literal)
+        self.vars.create_literal(self.static_origin, literal)
     }
 
     pub fn void_literal(&self) -> VarId {
         self.vars.void_literal()
     }
 
-    pub fn initialize_var_with_value(&self, var: VarId, value_sources: Vec<VarId>) -> CheckerResult {
+    /// `static` and `const` items live in the static origin, so borrows of them are `'static`
+    pub fn create_static(&mut self, is_mut: bool, identifier: String) -> VarId {
+        self.vars
+            .create_static(self.static_origin, is_mut, identifier)
+    }
+
+    /// The var of a `static` item. Unlike a `const`, which is a new value wherever it is used, a `static` is a
+    /// single place, so all its uses share a var and conflicting borrows of a `static mut` are found.
+    pub fn static_var(&mut self, item: hir::Static, is_mut: bool, identifier: String) -> VarId {
+        if let Some(var) = self.statics.get(&item) {
+            return *var;
+        }
+        let var = self.create_static(is_mut, identifier);
+        self.statics.insert(item, var);
+        var
+    }
+
+    /// `range` is the source of the initialization (e.g. the `let` statement). Initializing the value behind a
+    /// mutable reference stores the value in the place the reference borrows.
+    pub fn initialize_var_with_value(
+        &self,
+        var: VarId,
+        value_sources: Vec<VarId>,
+        range: TextRange,
+    ) -> CheckerResult {
+        self.vars
+            .resolve_var(var)
+            .borrow_mut()
+            .initialize_with_values(value_sources, range, &self.vars)?;
+        self.vars.write_through(var, range)
+    }
+
+    /// Assigns `value` to a field of `place`, which from now on holds the borrows of `value` in addition to the ones
+    /// of its other fields
+    pub fn assign_field(&mut self, place: VarId, value: VarId, range: TextRange) -> CheckerResult {
+        let field = self.create_expr_tmp("<field>".to_string());
+        self.initialize_var_with_value(field, vec![value], range)?;
+        self.vars
+            .resolve_var(place)
+            .borrow_mut()
+            .reset_field(range, &self.vars)?;
+        let (borrows, loans) = self.vars.borrows(field);
+        self.vars.store_borrows(&borrows, &loans, place, range)
+    }
+
+    /// The places `reference` borrows may hold the borrows of `value` from now on, e.g. because a callee whose
+    /// signature is invariant in their lifetime may store `value` in them
+    pub fn store_through(&self, reference: VarId, value: VarId, range: TextRange) -> CheckerResult {
+        let (borrows, loans) = self.vars.borrows(value);
+        let (places, _) = self.vars.borrows(reference);
+        for place in places {
+            self.vars
+                .store_borrows(&borrows, &loans, place.target(), range)?;
+        }
+        Ok(())
+    }
+
+    /// Initializes `var` with a struct or enum variant built from `fields`. Each field comes with the lifetime
+    /// parameters its type mentions, `None` if they are unknown.
+    pub fn initialize_var_with_fields(
+        &self,
+        var: VarId,
+        fields: Vec<(VarId, Option<Vec<String>>)>,
+        range: TextRange,
+    ) -> CheckerResult {
+        self.vars
+            .resolve_var(var)
+            .borrow_mut()
+            .initialize_with_fields(fields, range, &self.vars)
+    }
+
+    /// Initializes `var` with a field of `source`, which takes the borrows `source` holds through the lifetime
+    /// parameters the field's type mentions (all of them if they are unknown)
+    pub fn initialize_var_with_projection(
+        &self,
+        var: VarId,
+        source: VarId,
+        lifetimes: Option<Vec<String>>,
+        range: TextRange,
+    ) -> CheckerResult {
         self.vars
             .resolve_var(var)
             .borrow_mut()
-            .initialize_with_values(value_sources, &self.vars)
+            .initialize_with_projection(source, lifetimes, range, &self.vars)
     }
 
     pub fn initialize_var_with_borrow(
@@ -90,24 +420,142 @@ impl Checker {
         var: VarId,
         borrowed_vars: Vec<VarId>,
         is_mut: bool,
+        range: TextRange,
     ) -> CheckerResult {
         self.vars
             .resolve_var(var)
             .borrow_mut()
-            .initialize_with_borrows(is_mut, borrowed_vars, &self.vars)
+            .initialize_with_borrows(is_mut, borrowed_vars, range, &self.vars)
     }
 
-    pub fn get_deref_var(&mut self, var: VarId) -> VarId {
-        self.vars.get_deref_var(var)
+    /// Initializes `var` with a two-phase mutable borrow of `borrowed_var`, which has to be activated by
+    /// `activate_reservation` where it is used, e.g. at the call of an auto-ref'd method
+    pub fn initialize_var_with_reservation(
+        &self,
+        var: VarId,
+        borrowed_var: VarId,
+        range: TextRange,
+    ) -> CheckerResult {
+        self.vars
+            .resolve_var(var)
+            .borrow_mut()
+            .initialize_with_reservation(borrowed_var, range, &self.vars)
+    }
+
+    pub fn activate_reservation(&self, var: VarId, range: TextRange) {
+        self.vars
+            .resolve_var(var)
+            .borrow()
+            .activate_reservations(range, &self.vars)
+    }
+
+    pub fn get_deref_var(&mut self, var: VarId, is_copy: bool) -> VarId {
+        self.vars.get_deref_var(var, is_copy)
+    }
+
+    /// See `Vars::get_field_var`
+    pub fn get_field_var(&mut self, var: VarId, path: &str) -> VarId {
+        self.vars.get_field_var(var, path)
+    }
+
+    /// Checks that `var` can be used at `range` and records the use
+    pub fn check_var_usable(&self, var: VarId, range: TextRange) -> CheckerResult {
+        let mut var = self.vars.resolve_var(var).borrow_mut();
+        var.assert_usable()?;
+        var.mark_used(range, &self.vars);
+        Ok(())
     }
 
-    pub fn check_var_usable(&self, var: VarId) -> CheckerResult {
-        self.vars.resolve_var(var).borrow().assert_usable()
+    /// Checks that the value of `var` can be read at `range` and records the use. Reading ends a mutable borrow
+    /// of `var`, so the mutable borrower is invalidated.
+    pub fn read_var(&self, var: VarId, range: TextRange) -> CheckerResult {
+        let mut var = self.vars.resolve_var(var).borrow_mut();
+        var.assert_usable()?;
+        var.read(range, &self.vars);
+        var.mark_used(range, &self.vars);
+        Ok(())
     }
 
     pub fn static_origin(&self) -> OriginId {
         self.static_origin
     }
+
+    /// Checks that `var` only borrows vars living in the static origin, e.g. because it is passed for a parameter
+    /// that the callee bounds by `'static` (`F: 'static`)
+    pub fn require_static(&self, var: VarId) -> CheckerResult {
+        self.vars.resolve_var(var).borrow().validate_for_origin(
+            self.static_origin,
+            &self.origins,
+            &self.vars,
+        )
+    }
+
+    /// Where the borrow of `borrowed` that `var` holds has been created, if it holds one
+    pub fn borrowed_at(&self, var: VarId, borrowed: VarId) -> Option<TextRange> {
+        self.vars.loan_created_at(var, borrowed)
+    }
+
+    /// Records that the value of `var` holds data of the lifetime parameters `origins`, e.g. because it is a
+    /// parameter of type `&'a T`
+    pub fn carry_origins(&self, var: VarId, origins: Vec<OriginId>) {
+        self.vars.resolve_var(var).borrow_mut().carry(origins);
+    }
+
+    /// Checks that the hidden type of the opaque type `opaque`, the value of `var`, only holds data of lifetimes that
+    /// outlive one of the lifetimes in its bounds, `captured`. Without any, its data must be `'static`.
+    pub fn check_captures(&self, var: VarId, opaque: &str, captured: &[OriginId]) -> CheckerResult {
+        if captured.is_empty() {
+            return self.check_lifetime_requirement(var, &[self.static_origin], "'static");
+        }
+        match self.uncaptured(var, captured) {
+            Some((origin, _)) => Err(CheckerError::HiddenTypeCaptures(
+                opaque.to_string(),
+                self.lifetime_name(origin),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the value of `var` only holds data of lifetimes that outlive one of `bounds`, e.g. the lifetime
+    /// bound of a returned trait object. `requirement` is how the bound is written.
+    pub fn check_lifetime_requirement(
+        &self,
+        var: VarId,
+        bounds: &[OriginId],
+        requirement: &str,
+    ) -> CheckerResult {
+        match self.uncaptured(var, bounds) {
+            Some((origin, identifier)) => Err(CheckerError::LifetimeRequirement(
+                identifier,
+                var,
+                self.lifetime_name(origin),
+                requirement.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The first lifetime parameter whose data `var` holds that doesn't outlive any of `bounds`, with the parameter
+    /// that holds it
+    fn uncaptured(&self, var: VarId, bounds: &[OriginId]) -> Option<(OriginId, String)> {
+        self.vars
+            .resolve_var(var)
+            .borrow()
+            .carried_origins(&self.vars)
+            .into_iter()
+            .find(|(origin, _)| {
+                !bounds
+                    .iter()
+                    .any(|bound| self.origins.outlives(*origin, *bound))
+            })
+    }
+
+    fn lifetime_name(&self, origin: OriginId) -> String {
+        match self.origins.kind(origin) {
+            OriginKind::Signature(name) => name.clone(),
+            _ => "'static".to_string(),
+        }
+    }
 }
 
 impl Display for Checker {
@@ -116,26 +564,409 @@ impl Display for Checker {
     }
 }
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum CheckerError {
-    #[error("Local '{0}' {1} is not valid anymore")]
-    Invalid(String, VarId),
+    #[error("{2}")]
+    Invalid(String, VarId, Invalidation),
 
-    #[error("Local '{0}' {1} has not yet been initialized")]
+    #[error("use of possibly-uninitialized `{0}`")]
     Uninitialized(String, VarId),
 
-    #[error("Local '{0}' {1} has been moved")]
+    #[error("use of moved value: `{0}`")]
     Moved(String, VarId),
 
-    #[error("Local '{0}' {1} is immutable, so exactly one assignment is allowed")]
+    #[error("cannot move out of `{0}` which is behind a reference")]
+    MovedOutOfBorrow(String, VarId),
+
+    #[error("cannot assign twice to immutable variable `{0}`")]
     ImmutableAssigned(String, VarId),
 
-    #[error("Local '{0}' {1} is immutable, so it cannot be borrowed mutably")]
+    #[error("cannot borrow `{0}` as mutable, as it is not declared as mutable")]
     ImmutableBorrowedMutable(String, VarId),
 
-    #[error("Local '{0}' {1} is not valid for origin {2}")]
+    #[error("`{0}` does not live long enough")]
     InvalidOrigin(String, VarId, OriginId),
 
+    #[error("cannot return reference to local variable `{0}`")]
+    ReturnsLocal(String, VarId),
+
+    #[error("hidden type for `{0}` captures lifetime that does not appear in bounds")]
+    HiddenTypeCaptures(String, String),
+
+    #[error("`{0}` has {} but it needs to satisfy a `{3}` lifetime requirement", describe_lifetime(.2))]
+    LifetimeRequirement(String, VarId, String, String),
+
+    #[error("`else` clause of `let...else` does not diverge")]
+    ElseNotDiverging,
+
     #[error("Outermost scope left")]
     OutermostScopeLeft,
 }
+
+impl CheckerError {
+    /// The code of the matching rustc error (e.g. `E0499`)
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            CheckerError::Invalid(_, _, invalidation) => Some(invalidation.cause.code()),
+            CheckerError::Uninitialized(..) => Some("E0381"),
+            CheckerError::Moved(..) => Some("E0382"),
+            CheckerError::MovedOutOfBorrow(..) => Some("E0507"),
+            CheckerError::ImmutableAssigned(..) => Some("E0384"),
+            CheckerError::ImmutableBorrowedMutable(..) => Some("E0596"),
+            CheckerError::InvalidOrigin(..) => Some("E0597"),
+            CheckerError::ReturnsLocal(..) => Some("E0515"),
+            CheckerError::HiddenTypeCaptures(..) => Some("E0700"),
+            CheckerError::LifetimeRequirement(.., requirement) if requirement == "'static" => {
+                Some("E0759")
+            }
+            CheckerError::LifetimeRequirement(..) => None,
+            CheckerError::ElseNotDiverging => Some("E0308"),
+            CheckerError::OutermostScopeLeft => None,
+        }
+    }
+
+    /// The text `rustc --explain` prints for this error's code
+    pub fn explanation(&self) -> Option<&'static str> {
+        self.code().and_then(explain)
+    }
+
+    pub fn kind(&self) -> CheckerErrorKind {
+        match self {
+            CheckerError::Invalid(..) => CheckerErrorKind::Invalid,
+            CheckerError::Uninitialized(..) => CheckerErrorKind::Uninitialized,
+            CheckerError::Moved(..) => CheckerErrorKind::Moved,
+            CheckerError::MovedOutOfBorrow(..) => CheckerErrorKind::MovedOutOfBorrow,
+            CheckerError::ImmutableAssigned(..) => CheckerErrorKind::ImmutableAssigned,
+            CheckerError::ImmutableBorrowedMutable(..) => {
+                CheckerErrorKind::ImmutableBorrowedMutable
+            }
+            CheckerError::InvalidOrigin(..) => CheckerErrorKind::InvalidOrigin,
+            CheckerError::ReturnsLocal(..) => CheckerErrorKind::ReturnsLocal,
+            CheckerError::HiddenTypeCaptures(..) => CheckerErrorKind::HiddenTypeCaptures,
+            CheckerError::LifetimeRequirement(..) => CheckerErrorKind::LifetimeRequirement,
+            CheckerError::ElseNotDiverging => CheckerErrorKind::ElseNotDiverging,
+            CheckerError::OutermostScopeLeft => CheckerErrorKind::OutermostScopeLeft,
+        }
+    }
+
+    /// Applies `map` to every source range contained in the error
+    pub fn map_ranges(mut self, map: impl Fn(TextRange) -> TextRange) -> Self {
+        if let CheckerError::Invalid(_, _, invalidation) = &mut self {
+            invalidation.range = map(invalidation.range);
+            for step in &mut invalidation.chain {
+                step.range = step.range.map(&map);
+            }
+        }
+        self
+    }
+
+    /// Applies `map` to the lines the shadowed locals named in the error are declared on, see `shadowed_identifier`
+    pub fn map_lines(self, map: impl Fn(usize) -> usize) -> Self {
+        let name = |name: &str| map_shadowed_lines(name, &map);
+        match self {
+            CheckerError::Invalid(used, var, mut invalidation) => {
+                invalidation.target = name(&invalidation.target);
+                for step in &mut invalidation.chain {
+                    step.borrower = name(&step.borrower);
+                    step.borrowed = name(&step.borrowed);
+                }
+                CheckerError::Invalid(name(&used), var, invalidation)
+            }
+            CheckerError::Uninitialized(used, var) => CheckerError::Uninitialized(name(&used), var),
+            CheckerError::Moved(used, var) => CheckerError::Moved(name(&used), var),
+            CheckerError::MovedOutOfBorrow(used, var) => {
+                CheckerError::MovedOutOfBorrow(name(&used), var)
+            }
+            CheckerError::ImmutableAssigned(used, var) => {
+                CheckerError::ImmutableAssigned(name(&used), var)
+            }
+            CheckerError::ImmutableBorrowedMutable(used, var) => {
+                CheckerError::ImmutableBorrowedMutable(name(&used), var)
+            }
+            CheckerError::InvalidOrigin(used, var, origin) => {
+                CheckerError::InvalidOrigin(name(&used), var, origin)
+            }
+            CheckerError::ReturnsLocal(used, var) => CheckerError::ReturnsLocal(name(&used), var),
+            CheckerError::LifetimeRequirement(used, var, lifetime, requirement) => {
+                CheckerError::LifetimeRequirement(name(&used), var, lifetime, requirement)
+            }
+            error => error,
+        }
+    }
+}
+
+/// The identifier of a local that is shadowed by another one of the same name. The line is part of the identifier,
+/// results that are moved to other lines have to be updated with `map_shadowed_lines`.
+pub(crate) fn shadowed_identifier(name: &str, line: usize) -> String {
+    format!("{}{}{})", name, SHADOWED, line)
+}
+
+const SHADOWED: &str = " (shadowed, declared on line ";
+
+/// Applies `map` to the lines in the identifiers of shadowed locals in `text`
+pub(crate) fn map_shadowed_lines(text: &str, map: impl Fn(usize) -> usize) -> String {
+    let mut mapped = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(SHADOWED) {
+        let (before, after) = rest.split_at(start + SHADOWED.len());
+        mapped += before;
+        let end = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        match after[..end].parse() {
+            Ok(line) => mapped += &map(line).to_string(),
+            Err(_) => mapped += &after[..end],
+        }
+        rest = &after[end..];
+    }
+    mapped + rest
+}
+
+/// E.g. "an anonymous lifetime `'_`" or "lifetime `'a`"
+fn describe_lifetime(lifetime: &str) -> String {
+    if lifetime == "'_" {
+        "an anonymous lifetime `'_`".to_string()
+    } else {
+        format!("lifetime `{}`", lifetime)
+    }
+}
+
+/// The variants of `CheckerError` without their fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheckerErrorKind {
+    Invalid,
+    Uninitialized,
+    Moved,
+    MovedOutOfBorrow,
+    ImmutableAssigned,
+    ImmutableBorrowedMutable,
+    InvalidOrigin,
+    ReturnsLocal,
+    HiddenTypeCaptures,
+    LifetimeRequirement,
+    ElseNotDiverging,
+    OutermostScopeLeft,
+}
+
+impl CheckerErrorKind {
+    pub const ALL: [CheckerErrorKind; 12] = [
+        CheckerErrorKind::Invalid,
+        CheckerErrorKind::Uninitialized,
+        CheckerErrorKind::Moved,
+        CheckerErrorKind::MovedOutOfBorrow,
+        CheckerErrorKind::ImmutableAssigned,
+        CheckerErrorKind::ImmutableBorrowedMutable,
+        CheckerErrorKind::InvalidOrigin,
+        CheckerErrorKind::ReturnsLocal,
+        CheckerErrorKind::HiddenTypeCaptures,
+        CheckerErrorKind::LifetimeRequirement,
+        CheckerErrorKind::ElseNotDiverging,
+        CheckerErrorKind::OutermostScopeLeft,
+    ];
+
+    /// The variant's name, e.g. `ImmutableAssigned`
+    pub fn name(self) -> &'static str {
+        match self {
+            CheckerErrorKind::Invalid => "Invalid",
+            CheckerErrorKind::Uninitialized => "Uninitialized",
+            CheckerErrorKind::Moved => "Moved",
+            CheckerErrorKind::MovedOutOfBorrow => "MovedOutOfBorrow",
+            CheckerErrorKind::ImmutableAssigned => "ImmutableAssigned",
+            CheckerErrorKind::ImmutableBorrowedMutable => "ImmutableBorrowedMutable",
+            CheckerErrorKind::InvalidOrigin => "InvalidOrigin",
+            CheckerErrorKind::ReturnsLocal => "ReturnsLocal",
+            CheckerErrorKind::HiddenTypeCaptures => "HiddenTypeCaptures",
+            CheckerErrorKind::LifetimeRequirement => "LifetimeRequirement",
+            CheckerErrorKind::ElseNotDiverging => "ElseNotDiverging",
+            CheckerErrorKind::OutermostScopeLeft => "OutermostScopeLeft",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            CheckerErrorKind::Invalid => {
+                "A borrow is used after a conflicting access to the borrowed value invalidated it"
+            }
+            CheckerErrorKind::Uninitialized => "A variable is used before it has been initialized",
+            CheckerErrorKind::Moved => "A value is used after it has been moved",
+            CheckerErrorKind::MovedOutOfBorrow => "A value is moved out from behind a reference",
+            CheckerErrorKind::ImmutableAssigned => {
+                "An immutable variable is assigned to more than once"
+            }
+            CheckerErrorKind::ImmutableBorrowedMutable => {
+                "An immutable variable is borrowed mutably"
+            }
+            CheckerErrorKind::InvalidOrigin => "A borrow outlives the value it borrows",
+            CheckerErrorKind::ReturnsLocal => "A function returns a reference to a local variable",
+            CheckerErrorKind::HiddenTypeCaptures => {
+                "A returned opaque type captures a lifetime that its bounds don't mention"
+            }
+            CheckerErrorKind::LifetimeRequirement => {
+                "A returned value holds data that doesn't live as long as a lifetime bound requires"
+            }
+            CheckerErrorKind::ElseNotDiverging => {
+                "The `else` block of a `let...else` statement can fall through"
+            }
+            CheckerErrorKind::OutermostScopeLeft => {
+                "The checker left the function's outermost scope (an internal error)"
+            }
+        }
+    }
+}
+
+/// Why a var has been invalidated: `target` is the var whose access at `range` ended the borrow.
+/// `chain` leads from the borrow of `target` to the invalidated var, one borrow at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invalidation {
+    pub cause: InvalidationCause,
+    pub target: String,
+    pub range: TextRange,
+    pub chain: Vec<BorrowStep>,
+}
+
+/// `borrower` borrows `borrowed` since `range`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorrowStep {
+    pub borrower: String,
+    pub borrowed: String,
+    pub is_mut: bool,
+    pub range: Option<TextRange>,
+}
+
+impl Invalidation {
+    /// Where rustc reports the error: at the conflicting access or, if the borrowed var has been dropped, at the borrow
+    pub fn primary_range(&self) -> TextRange {
+        match self.cause {
+            InvalidationCause::Dropped | InvalidationCause::TemporaryDropped => self
+                .chain
+                .first()
+                .and_then(|step| step.range)
+                .unwrap_or(self.range),
+            _ => self.range,
+        }
+    }
+
+    /// Renders the whole chain, e.g. "`y` borrows `x` mutably (line 3); `x` is borrowed mutably again (line 4),
+    /// invalidating `y`; `y` used here (line 5)". `line` maps ranges to line numbers.
+    pub fn describe(
+        &self,
+        used: &str,
+        used_range: TextRange,
+        line: impl Fn(TextRange) -> usize,
+    ) -> String {
+        let at = |range: Option<TextRange>| match range {
+            Some(range) => format!(" (line {})", line(range)),
+            None => String::new(),
+        };
+
+        let mut parts = self
+            .chain
+            .iter()
+            .map(|step| {
+                format!(
+                    "`{}` borrows `{}` {}{}",
+                    step.borrower,
+                    step.borrowed,
+                    if step.is_mut { "mutably" } else { "immutably" },
+                    at(step.range)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let access = match self.cause {
+            InvalidationCause::MutBorrowedTwice => "is borrowed mutably again",
+            InvalidationCause::MutBorrowedWhileShared => "is borrowed mutably",
+            InvalidationCause::SharedBorrowedWhileMut => "is borrowed immutably",
+            InvalidationCause::Assigned => "is assigned to",
+            InvalidationCause::Used => "is used",
+            InvalidationCause::Moved => "is moved",
+            InvalidationCause::Dropped => "is dropped",
+            InvalidationCause::TemporaryDropped => "is dropped at the end of its statement",
+        };
+        let invalidated = self
+            .chain
+            .iter()
+            .map(|step| format!("`{}`", step.borrower))
+            .collect::<Vec<_>>()
+            .join(", which invalidates ");
+        parts.push(format!(
+            "`{}` {}{}, invalidating {}",
+            self.target,
+            access,
+            at(Some(self.range)),
+            invalidated
+        ));
+        parts.push(format!("`{}` used here{}", used, at(Some(used_range))));
+
+        parts.join("; ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidationCause {
+    MutBorrowedTwice,
+    MutBorrowedWhileShared,
+    SharedBorrowedWhileMut,
+    Assigned,
+    Used,
+    Moved,
+    Dropped,
+    /// A temporary is dropped at the end of its statement
+    TemporaryDropped,
+}
+
+impl InvalidationCause {
+    pub fn code(self) -> &'static str {
+        match self {
+            InvalidationCause::MutBorrowedTwice => "E0499",
+            InvalidationCause::MutBorrowedWhileShared
+            | InvalidationCause::SharedBorrowedWhileMut => "E0502",
+            InvalidationCause::Assigned => "E0506",
+            InvalidationCause::Used => "E0503",
+            InvalidationCause::Moved => "E0505",
+            InvalidationCause::Dropped => "E0597",
+            InvalidationCause::TemporaryDropped => "E0716",
+        }
+    }
+}
+
+impl Display for Invalidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.cause {
+            InvalidationCause::MutBorrowedTwice => write!(
+                f,
+                "cannot borrow `{}` as mutable more than once at a time",
+                self.target
+            ),
+            InvalidationCause::MutBorrowedWhileShared => write!(
+                f,
+                "cannot borrow `{}` as mutable because it is also borrowed as immutable",
+                self.target
+            ),
+            InvalidationCause::SharedBorrowedWhileMut => write!(
+                f,
+                "cannot borrow `{}` as immutable because it is also borrowed as mutable",
+                self.target
+            ),
+            InvalidationCause::Assigned => write!(
+                f,
+                "cannot assign to `{}` because it is borrowed",
+                self.target
+            ),
+            InvalidationCause::Used => write!(
+                f,
+                "cannot use `{}` because it was mutably borrowed",
+                self.target
+            ),
+            InvalidationCause::Moved => write!(
+                f,
+                "cannot move out of `{}` because it is borrowed",
+                self.target
+            ),
+            InvalidationCause::Dropped => write!(f, "`{}` does not live long enough", self.target),
+            InvalidationCause::TemporaryDropped => {
+                write!(f, "temporary value dropped while borrowed")
+            }
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Structured diagnostics in the JSON shape of rustc (`--error-format=json`) and cargo (`--message-format=json`),
+//! so tools that parse cargo's output can consume the checker's results unchanged.
+
+use serde::Serialize;
+use syntax::TextRange;
+
+use crate::{
+    line_of, polonius_checker::PoloniusError, CheckError, CheckerError, Invalidation,
+    InvalidationCause,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+/// A diagnostic like rustc emits it. Children are notes attached to the diagnostic and have no children of their own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub message: String,
+    pub code: Option<DiagnosticCode>,
+    pub level: Level,
+    pub spans: Vec<DiagnosticSpan>,
+    pub children: Vec<Diagnostic>,
+    /// The diagnostic as it is printed in the terminal. Only set on top-level diagnostics.
+    pub rendered: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticCode {
+    /// e.g. `E0499`
+    pub code: String,
+    /// The text `rustc --explain` prints for the code
+    pub explanation: Option<&'static str>,
+}
+
+/// A labelled source range. Lines and columns are one-based, columns count characters and the ends are exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub byte_start: u32,
+    pub byte_end: u32,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    /// The span the error is reported at, as opposed to spans giving context
+    pub is_primary: bool,
+    /// The source lines covered by the span
+    pub text: Vec<DiagnosticSpanLine>,
+    pub label: Option<String>,
+    pub suggested_replacement: Option<String>,
+    pub suggestion_applicability: Option<String>,
+    /// Always `null`, the checker doesn't see macro expansions
+    pub expansion: Option<()>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticSpanLine {
+    pub text: String,
+    /// The highlighted columns of this line, one-based and exclusive like the span's columns
+    pub highlight_start: usize,
+    pub highlight_end: usize,
+}
+
+/// A diagnostic wrapped like cargo reports it for a target of a package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompilerMessage {
+    /// Always `compiler-message`
+    pub reason: &'static str,
+    pub package_id: String,
+    pub manifest_path: String,
+    pub target: Target,
+    pub message: Diagnostic,
+}
+
+/// The cargo target a diagnostic belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Target {
+    /// `lib` or `bin`
+    pub kind: Vec<String>,
+    pub crate_types: Vec<String>,
+    pub name: String,
+    pub src_path: String,
+    pub edition: String,
+    pub doctest: bool,
+    pub test: bool,
+}
+
+impl CompilerMessage {
+    pub fn new(
+        package_id: String,
+        manifest_path: String,
+        target: Target,
+        message: Diagnostic,
+    ) -> Self {
+        Self {
+            reason: "compiler-message",
+            package_id,
+            manifest_path,
+            target,
+            message,
+        }
+    }
+}
+
+impl Target {
+    /// The target of the crate `name` with the root file `src_path`. Crates rooted in a `lib.rs` are libraries,
+    /// all others binaries.
+    pub fn new(name: String, src_path: String) -> Self {
+        let kind = if src_path.ends_with("lib.rs") {
+            "lib"
+        } else {
+            "bin"
+        };
+        Self {
+            kind: vec![kind.to_string()],
+            crate_types: vec![kind.to_string()],
+            name,
+            src_path,
+            edition: "2021".to_string(),
+            doctest: kind == "lib",
+            test: true,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// An error without a code or spans, e.g. for a file that couldn't be checked at all
+    pub fn error(message: String) -> Self {
+        Self {
+            rendered: Some(format!("error: {}\n", message)),
+            message,
+            code: None,
+            level: Level::Error,
+            spans: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn note(message: String) -> Self {
+        Self {
+            message,
+            code: None,
+            level: Level::Note,
+            spans: Vec::new(),
+            children: Vec::new(),
+            rendered: None,
+        }
+    }
+
+    /// The span the error is reported at
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|span| span.is_primary)
+    }
+}
+
+impl CheckError {
+    /// Converts the error into a rustc diagnostic. `file_name` is used for the spans, `source` is the checked code.
+    pub fn diagnostic(&self, file_name: &str, source: &str) -> Diagnostic {
+        let span = |range: TextRange, is_primary: bool, label: String| {
+            DiagnosticSpan::new(file_name, source, range, is_primary, Some(label))
+        };
+
+        let mut spans = Vec::new();
+        let mut children = Vec::new();
+        match self {
+            CheckError::Borrowcheck {
+                error: CheckerError::Invalid(used, _, invalidation),
+                range,
+            } => {
+                let (primary, secondary) = invalidation_labels(invalidation);
+                for (index, step) in invalidation.chain.iter().enumerate() {
+                    if let Some(step_range) = step.range {
+                        let label = if index == 0 {
+                            secondary.clone()
+                        } else {
+                            format!(
+                                "`{}` borrows `{}` {} here",
+                                step.borrower,
+                                step.borrowed,
+                                if step.is_mut { "mutably" } else { "immutably" }
+                            )
+                        };
+                        spans.push(span(
+                            step_range,
+                            step_range == invalidation.primary_range(),
+                            label,
+                        ));
+                    }
+                }
+                spans.push(span(
+                    invalidation.range,
+                    invalidation.range == invalidation.primary_range(),
+                    primary,
+                ));
+                spans.push(span(
+                    *range,
+                    false,
+                    format!("borrow later used here, by `{}`", used),
+                ));
+                children.push(Diagnostic::note(invalidation.describe(
+                    used,
+                    *range,
+                    |range| line_of(source, range),
+                )));
+            }
+            CheckError::Borrowcheck { error, range } => {
+                if let Some(label) = error_label(error) {
+                    spans.push(span(*range, true, label));
+                }
+            }
+            CheckError::Polonius(
+                PoloniusError::LoanInvalidated {
+                    range: Some(range), ..
+                }
+                | PoloniusError::Moved {
+                    range: Some(range), ..
+                },
+            ) => spans.push(DiagnosticSpan::new(file_name, source, *range, true, None)),
+            CheckError::Unsupported { range, .. } => {
+                spans.push(DiagnosticSpan::new(file_name, source, *range, true, None))
+            }
+            CheckError::Polonius(_) | CheckError::Workspace(_) | CheckError::Panicked(_) => {}
+        }
+        // Like rustc, the primary span comes first and the others follow in source order
+        spans.sort_by_key(|span| (!span.is_primary, span.byte_start));
+
+        let mut diagnostic = Diagnostic {
+            message: self.to_string(),
+            code: self.code().map(|code| DiagnosticCode {
+                code: code.to_string(),
+                explanation: self.explanation(),
+            }),
+            level: Level::Error,
+            spans,
+            children,
+            rendered: None,
+        };
+        diagnostic.rendered = Some(diagnostic.render(false) + "\n");
+        diagnostic
+    }
+}
+
+/// The labels of the conflicting access and of the borrow it conflicts with, worded like rustc's
+fn invalidation_labels(invalidation: &Invalidation) -> (String, String) {
+    let target = &invalidation.target;
+    let (access, borrow) = match invalidation.cause {
+        InvalidationCause::MutBorrowedTwice => (
+            "second mutable borrow occurs here".to_string(),
+            "first mutable borrow occurs here".to_string(),
+        ),
+        InvalidationCause::MutBorrowedWhileShared => (
+            "mutable borrow occurs here".to_string(),
+            "immutable borrow occurs here".to_string(),
+        ),
+        InvalidationCause::SharedBorrowedWhileMut => (
+            "immutable borrow occurs here".to_string(),
+            "mutable borrow occurs here".to_string(),
+        ),
+        InvalidationCause::Assigned => (
+            format!(
+                "`{}` is assigned to here but it was already borrowed",
+                target
+            ),
+            format!("`{}` is borrowed here", target),
+        ),
+        InvalidationCause::Used => (
+            format!("use of borrowed `{}`", target),
+            format!("`{}` is borrowed here", target),
+        ),
+        InvalidationCause::Moved => (
+            format!("move out of `{}` occurs here", target),
+            format!("borrow of `{}` occurs here", target),
+        ),
+        InvalidationCause::Dropped => (
+            format!("`{}` dropped here while still borrowed", target),
+            "borrowed value does not live long enough".to_string(),
+        ),
+        InvalidationCause::TemporaryDropped => (
+            "temporary value is freed at the end of this statement".to_string(),
+            "creates a temporary value which is freed while still in use".to_string(),
+        ),
+    };
+    (access, borrow)
+}
+
+fn error_label(error: &CheckerError) -> Option<String> {
+    Some(match error {
+        CheckerError::Invalid(..) => unreachable!("invalidations are labelled by their chain"),
+        CheckerError::Uninitialized(name, _) => {
+            format!("`{}` used here but it isn't initialized", name)
+        }
+        CheckerError::Moved(..) => "value used here after move".to_string(),
+        CheckerError::MovedOutOfBorrow(..) => {
+            "move occurs because the value does not implement the `Copy` trait".to_string()
+        }
+        CheckerError::ImmutableAssigned(..) => {
+            "cannot assign twice to immutable variable".to_string()
+        }
+        CheckerError::ImmutableBorrowedMutable(..) => "cannot borrow as mutable".to_string(),
+        CheckerError::InvalidOrigin(..) => "borrowed value does not live long enough".to_string(),
+        CheckerError::ReturnsLocal(..) => {
+            "returns a reference to data owned by the current function".to_string()
+        }
+        CheckerError::HiddenTypeCaptures(_, lifetime) => {
+            format!("hidden type captures the lifetime `{}`", lifetime)
+        }
+        CheckerError::LifetimeRequirement(.., requirement) => format!(
+            "...is returned here, which requires it to live as long as `{}`",
+            requirement
+        ),
+        CheckerError::ElseNotDiverging => "expected `!`, found `()`".to_string(),
+        CheckerError::OutermostScopeLeft => return None,
+    })
+}
+
+impl DiagnosticSpan {
+    fn new(
+        file_name: &str,
+        source: &str,
+        range: TextRange,
+        is_primary: bool,
+        label: Option<String>,
+    ) -> Self {
+        let (line_start, column_start) = line_column(source, range.start().into());
+        let (line_end, column_end) = line_column(source, range.end().into());
+
+        let text = source
+            .lines()
+            .enumerate()
+            .skip(line_start - 1)
+            .take(line_end - line_start + 1)
+            .map(|(index, text)| DiagnosticSpanLine {
+                text: text.to_string(),
+                highlight_start: if index + 1 == line_start {
+                    column_start
+                } else {
+                    1
+                },
+                highlight_end: if index + 1 == line_end {
+                    column_end
+                } else {
+                    text.chars().count() + 1
+                },
+            })
+            .collect();
+
+        Self {
+            file_name: file_name.to_string(),
+            byte_start: range.start().into(),
+            byte_end: range.end().into(),
+            line_start,
+            line_end,
+            column_start,
+            column_end,
+            is_primary,
+            text,
+            label,
+            suggested_replacement: None,
+            suggestion_applicability: None,
+            expansion: None,
+        }
+    }
+}
+
+/// The one-based line and (character) column of `offset`
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
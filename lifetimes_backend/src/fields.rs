@@ -0,0 +1,117 @@
+//! The fields of structs and enum variants, and the lifetime parameters their types mention. A value holds the
+//! borrows of a field through these lifetime parameters, which is what lets a field access project them back out.
+
+use hir::{db::HirDatabase, Semantics};
+use syntax::ast::{self, AstNode, HasGenericParams, HasName};
+
+/// The declared fields of the struct or enum variant a path resolves to
+pub(crate) struct AdtFields {
+    fields: Option<ast::FieldList>,
+    generics: Option<ast::GenericParamList>,
+}
+
+impl AdtFields {
+    /// `None` if the path doesn't resolve to a struct or an enum variant
+    pub(crate) fn resolve<'db, DB: HirDatabase>(
+        path: &ast::Path,
+        sema: &Semantics<'db, DB>,
+    ) -> Option<Self> {
+        let (fields, adt) = match sema.resolve_path(path)? {
+            hir::PathResolution::Def(hir::ModuleDef::Adt(hir::Adt::Struct(def))) => {
+                let def = sema.source(def)?.value;
+                (def.field_list(), def.syntax().clone())
+            }
+            hir::PathResolution::Def(hir::ModuleDef::Variant(def)) => {
+                let def = sema.source(def)?.value;
+                (def.field_list(), def.syntax().clone())
+            }
+            _ => return None,
+        };
+        Some(Self {
+            fields,
+            generics: adt
+                .ancestors()
+                .find_map(ast::Adt::cast)
+                .and_then(|adt| adt.generic_param_list()),
+        })
+    }
+
+    /// The lifetime parameters of the field named `name`
+    pub(crate) fn named(&self, name: &str) -> Option<Vec<String>> {
+        match &self.fields {
+            Some(ast::FieldList::RecordFieldList(fields)) => fields
+                .fields()
+                .find(|field| {
+                    field
+                        .name()
+                        .map_or(false, |field| field.to_string() == name)
+                })
+                .and_then(|field| field.ty())
+                .and_then(|ty| type_lifetimes(&ty, self.generics.as_ref())),
+            _ => None,
+        }
+    }
+
+    /// The lifetime parameters of the `index`th field of a tuple struct or variant
+    pub(crate) fn positional(&self, index: usize) -> Option<Vec<String>> {
+        match &self.fields {
+            Some(ast::FieldList::TupleFieldList(fields)) => fields
+                .fields()
+                .nth(index)
+                .and_then(|field| field.ty())
+                .and_then(|ty| type_lifetimes(&ty, self.generics.as_ref())),
+            _ => None,
+        }
+    }
+}
+
+/// The lifetime parameters of the type of the field `field` accesses
+pub(crate) fn field_lifetimes<'db, DB: HirDatabase>(
+    field: &ast::FieldExpr,
+    sema: &Semantics<'db, DB>,
+) -> Option<Vec<String>> {
+    let source = sema.source(sema.resolve_field(field)?)?.value;
+    let (ty, node) = match &source {
+        hir::FieldSource::Named(field) => (field.ty()?, field.syntax()),
+        hir::FieldSource::Pos(field) => (field.ty()?, field.syntax()),
+    };
+    let generics = node
+        .ancestors()
+        .find_map(ast::Adt::cast)
+        .and_then(|adt| adt.generic_param_list());
+    type_lifetimes(&ty, generics.as_ref())
+}
+
+/// The lifetime parameters `ty` mentions, except `'static`. `None` if `ty` mentions a type parameter, which may
+/// hold borrows through any lifetime.
+fn type_lifetimes(ty: &ast::Type, generics: Option<&ast::GenericParamList>) -> Option<Vec<String>> {
+    let type_params = generics
+        .map(|generics| {
+            generics
+                .type_params()
+                .filter_map(|param| param.name())
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mentions_type_param = ty
+        .syntax()
+        .descendants()
+        .filter_map(ast::PathType::cast)
+        .filter_map(|path| path.path())
+        .any(|path| {
+            path.qualifier().is_none() && type_params.contains(&path.syntax().text().to_string())
+        });
+    if mentions_type_param {
+        return None;
+    }
+
+    let mut lifetimes = Vec::new();
+    for lifetime in ty.syntax().descendants().filter_map(ast::Lifetime::cast) {
+        let lifetime = lifetime.to_string();
+        if lifetime != "'static" && !lifetimes.contains(&lifetime) {
+            lifetimes.push(lifetime);
+        }
+    }
+    Some(lifetimes)
+}
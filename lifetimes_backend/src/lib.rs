@@ -1,137 +1,821 @@
+mod captures;
 mod checker;
+mod diagnostic;
+mod fields;
+mod lifetimes;
+mod origins;
 mod polonius_checker;
+mod render;
+mod sarif;
+mod session;
+mod signature;
+mod variance;
+mod workspace;
 
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
+pub use checker::{
+    explain, Borrow, BorrowStep, CheckerError, CheckerErrorKind, Invalidation, InvalidationCause,
+    Loan, LoanId, OriginId, OriginInfo, OriginKind, Snapshot, VarId, VarKind, VarSnapshot,
+    VarStatus,
+};
+pub use diagnostic::{
+    CompilerMessage, Diagnostic, DiagnosticCode, DiagnosticSpan, DiagnosticSpanLine, Level, Target,
+};
+pub use lifetimes::{FunctionLifetimes, Glyph, GutterCell, GutterLine, Lifetime, LifetimeKind};
+pub use origins::FunctionOrigins;
+pub use sarif::SarifLog;
+pub use session::Session;
+pub use workspace::{check_workspace, FileReport, WorkspaceOptions};
 
-use base_db::{CrateOrigin, Env};
-use checker::{Checker, CheckerError, VarId};
-use hir::{db::HirDatabase, CfgOptions, Semantics};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    path::Path,
+    sync::Arc,
+};
+
+use base_db::{CrateOrigin, Env, FileLoader};
+use captures::CaptureMode;
+use checker::{shadowed_identifier, Checker};
+use fields::AdtFields;
+use hir::{db::HirDatabase, CfgOptions, HirDisplay, Semantics};
 use ide::{AnalysisHost, Change, CrateGraph, Edition, FileId, SourceRoot};
 use log::info;
-use syntax::ast::{self, AstNode, HasName};
+use polonius_checker::{FactGenerator, PoloniusError};
+use signature::{Generics, ReturnBound, Signature};
+use syntax::{
+    ast::{self, AstNode, HasGenericParams, HasLoopBody, HasName, HasTypeBounds},
+    SyntaxKind, SyntaxNode, TextRange, TextSize,
+};
 
 use vfs::{file_set::FileSet, VfsPath};
 
 pub fn check(code: String) -> Result<(), CheckError> {
+    Session::with_code(code).check(MAIN_PATH)
+}
+
+/// The borrow states recorded while checking a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionTrace {
+    pub name: String,
+    /// One snapshot per statement and tail expression, in the order they were checked
+    pub statements: Vec<Snapshot>,
+}
+
+/// Like `check`, but also returns the borrow state after every statement.
+/// The trace of a function that failed to check ends at the last statement before the error.
+pub fn check_with_trace(code: String) -> (Vec<FunctionTrace>, Result<(), CheckError>) {
+    Session::with_code(code).check_with_trace(MAIN_PATH)
+}
+
+/// Computes the line ranges of every function's locals and loans, including lanes for rendering them side by side.
+/// The lifetimes of a function that failed to check end at the error.
+pub fn lifetimes(code: String) -> (Vec<FunctionLifetimes>, Result<(), CheckError>) {
+    Session::with_code(code).lifetimes(MAIN_PATH)
+}
+
+/// The origins of every function: its scopes and signature lifetimes, with the vars living in them.
+/// The origins of a function that failed to check are the ones created up to the error.
+pub fn origins(code: String) -> (Vec<FunctionOrigins>, Result<(), CheckError>) {
+    Session::with_code(code).origins(MAIN_PATH)
+}
+
+/// Checks every function with its own checker (set up by `setup`) and collects what `inspect` extracts from the
+/// checker afterwards. Stops at the first function that fails to check, but still inspects it.
+fn check_each_function<'db, DB: HirDatabase, T>(
+    sema: &Semantics<'db, DB>,
+    file: FileId,
+    setup: impl Fn(&mut Checker),
+    mut inspect: impl FnMut(&ast::Fn, &mut Checker) -> T,
+) -> (Vec<T>, Result<(), CheckError>) {
+    let file_node = sema.parse(file);
+
+    let mut inspected = Vec::new();
+    for function in checked_functions(&file_node) {
+        let mut checker = Checker::new();
+        setup(&mut checker);
+        let result = check_function(&function, &mut checker, sema);
+        inspected.push(inspect(&function, &mut checker));
+        if let Err(error) = result {
+            return (inspected, Err(error));
+        }
+    }
+
+    (inspected, Ok(()))
+}
+
+/// The functions of `file` that have a body, including methods and the functions of inline modules and of other
+/// functions' bodies
+pub(crate) fn checked_functions(file: &ast::SourceFile) -> Vec<ast::Fn> {
+    file.syntax()
+        .descendants()
+        .filter_map(ast::Fn::cast)
+        .filter(|function| function.body().is_some())
+        .collect()
+}
+
+/// Generates Polonius facts for every function in `code` and writes them to `<facts_dir>/<function>/*.facts`, see
+/// `facts_dir_name`
+pub fn dump_facts(code: String, facts_dir: &Path) -> Result<(), CheckError> {
+    let (host, file) = load_code(code);
+
+    let semantics = Semantics::new(host.raw_database());
+    let file_node = semantics.parse(file);
+
+    let mut names = HashSet::new();
+    for function in checked_functions(&file_node) {
+        let name = facts_dir_name(&function, &names);
+        names.insert(name.clone());
+        let (facts, _) = FactGenerator::new(&semantics, name).generate(&function)?;
+        facts.write_to_dir(facts_dir)?;
+    }
+
+    Ok(())
+}
+
+/// The name of the directory the facts of `function` are written to: its name, prefixed by the type of its `impl`
+/// block (e.g. `Foo-new`). If that is `taken` already, e.g. by a function of the same name in another module, the
+/// offset of the function is appended (e.g. `main-120`).
+fn facts_dir_name(function: &ast::Fn, taken: &HashSet<String>) -> String {
+    let name = function.name().unwrap().text().to_string();
+    let name = match function
+        .syntax()
+        .ancestors()
+        .find_map(ast::Impl::cast)
+        .and_then(|implementation| implementation.self_ty())
+    {
+        // Only keep the characters that are valid in file names everywhere
+        Some(self_ty) => {
+            self_ty
+                .syntax()
+                .text()
+                .to_string()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect::<String>()
+                + "-"
+                + &name
+        }
+        None => name,
+    };
+    if taken.contains(&name) {
+        format!(
+            "{}-{}",
+            name,
+            u32::from(function.syntax().text_range().start())
+        )
+    } else {
+        name
+    }
+}
+
+/// Runs Polonius on every function in `code`
+pub fn check_polonius(code: String) -> Result<(), CheckError> {
+    let options = CheckOptions {
+        backend: Backend::Polonius,
+        ..CheckOptions::default()
+    };
+    check_with_options(code, &options).1
+}
+
+/// Which borrow checker is run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The checker of this crate, which supports traces and lifetimes
+    Native,
+    /// Polonius, run on facts generated from the code
+    Polonius,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Native
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CheckOptions {
+    pub backend: Backend,
+    /// Only check the function with this path, e.g. `main` or `module::function`. A path with a single segment
+    /// matches functions of that name in every module.
+    pub function: Option<String>,
+    /// Record the borrow state after every statement (only supported by the native backend)
+    pub trace: bool,
+}
+
+/// Checks `code` as configured by `options`. Returns a trace for every checked function if `options.trace` is set.
+pub fn check_with_options(
+    code: String,
+    options: &CheckOptions,
+) -> (Vec<FunctionTrace>, Result<(), CheckError>) {
+    let (host, file) = load_code(code);
+    let semantics = Semantics::new(host.raw_database());
+    check_file(&semantics, file, &[], options)
+}
+
+/// Checks the functions of `file` that are selected by `options`. `module_path` is the path of the file's module,
+/// which `options.function` is matched against.
+fn check_file<'db, DB: HirDatabase>(
+    sema: &Semantics<'db, DB>,
+    file: FileId,
+    module_path: &[String],
+    options: &CheckOptions,
+) -> (Vec<FunctionTrace>, Result<(), CheckError>) {
+    let file_node = sema.parse(file);
+
+    let mut traces = Vec::new();
+    for function in checked_functions(&file_node) {
+        let name = function.name().unwrap().text().to_string();
+        if let Some(path) = &options.function {
+            let mut inline_modules = function
+                .syntax()
+                .ancestors()
+                .filter_map(ast::Module::cast)
+                .filter_map(|module| module.name())
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            inline_modules.reverse();
+            let function_path = [module_path, &inline_modules[..]].concat();
+            if !matches_function_path(path, &function_path, &name) {
+                continue;
+            }
+        }
+
+        let result = match options.backend {
+            Backend::Native => {
+                let mut checker = Checker::new();
+                if options.trace {
+                    checker.enable_trace();
+                }
+                let result = check_function(&function, &mut checker, sema);
+                if options.trace {
+                    traces.push(FunctionTrace {
+                        name,
+                        statements: checker.take_trace(),
+                    });
+                }
+                result
+            }
+            Backend::Polonius => FactGenerator::new(sema, name)
+                .generate(&function)
+                .and_then(|(facts, point_ranges)| {
+                    polonius_checker::check_facts(&facts, &point_ranges)
+                })
+                .map_err(CheckError::from),
+        };
+        if let Err(error) = result {
+            return (traces, Err(error));
+        }
+    }
+
+    (traces, Ok(()))
+}
+
+fn matches_function_path(path: &str, module_path: &[String], name: &str) -> bool {
+    let segments = path.split("::").collect::<Vec<_>>();
+    match segments.split_last() {
+        Some((last, [])) => *last == name,
+        Some((last, modules)) => *last == name && modules.iter().eq(module_path.iter()),
+        None => false,
+    }
+}
+
+/// Runs Polonius on facts in the layout produced by `rustc -Znll-facts` (e.g. `nll-facts/`)
+pub fn check_facts_dir(facts_dir: &Path) -> Result<(), CheckError> {
+    for facts in polonius_checker::read_facts_dir(facts_dir)? {
+        polonius_checker::check_facts(&facts, &HashMap::new())?;
+    }
+    Ok(())
+}
+
+/// The virtual path of the code passed to the functions that check a single file
+const MAIN_PATH: &str = "/main.rs";
+
+fn load_code(code: String) -> (AnalysisHost, FileId) {
     let mut host = AnalysisHost::new(None);
 
-    let mut file_set = FileSet::default();
     let file = FileId(0);
-    file_set.insert(file, VfsPath::new_virtual_path("/main.rs".to_string()));
-    let root = SourceRoot::new_local(file_set);
+    let mut initial_change = standalone_crates([(file, MAIN_PATH)]);
+    initial_change.change_file(file, Some(Arc::new(code)));
+    host.apply_change(initial_change);
 
+    (host, file)
+}
+
+/// A change that makes every file the root of its own crate, like `rustc <file>` would
+fn standalone_crates<'p>(files: impl IntoIterator<Item = (FileId, &'p str)>) -> Change {
+    let mut roots = Vec::new();
     let mut crate_graph = CrateGraph::default();
-    crate_graph.add_crate_root(
-        file,
-        Edition::Edition2021,
-        None,
-        None,
-        CfgOptions::default(),
-        CfgOptions::default(),
-        Env::default(),
-        Vec::new(),
-        CrateOrigin::Unknown,
-    );
-
-    let mut initial_change = Change::default();
-    initial_change.set_roots(vec![root]);
-    initial_change.set_crate_graph(crate_graph);
-    initial_change.change_file(FileId(0), Some(Arc::new(code.to_string())));
-    host.apply_change(initial_change);
+    for (file, path) in files {
+        let mut file_set = FileSet::default();
+        file_set.insert(file, VfsPath::new_virtual_path(path.to_string()));
+        roots.push(SourceRoot::new_local(file_set));
 
-    let semantics = Semantics::new(host.raw_database());
-    let file_node = semantics.parse(file);
+        crate_graph.add_crate_root(
+            file,
+            Edition::Edition2021,
+            None,
+            None,
+            CfgOptions::default(),
+            CfgOptions::default(),
+            Env::default(),
+            Vec::new(),
+            CrateOrigin::Unknown,
+        );
+    }
 
-    //dbg!(file_node.syntax());
+    let mut change = Change::default();
+    change.set_roots(roots);
+    change.set_crate_graph(crate_graph);
+    change
+}
 
+fn check_function<'db, DB: HirDatabase>(
+    function: &ast::Fn,
+    checker: &mut Checker,
+    sema: &Semantics<'db, DB>,
+) -> Result<(), CheckError> {
     let mut locals_map = HashMap::new();
-    let mut checker = Checker::new();
 
-    let main = file_node
-        .syntax()
-        .children()
-        .filter_map(ast::Fn::cast)
-        .find(|function| function.name().unwrap().text() == "main")
-        .expect("no function named 'main' found");
-
-    checker.enter_function(checker.static_origin());
-    process_block(
-        &main.body().unwrap(),
-        &mut checker,
-        &mut locals_map,
-        &semantics,
-    )?;
+    let static_origin = checker.static_origin();
+    checker.enter_function(static_origin, function.syntax().text_range());
+    let lifetimes = declare_lifetimes(function, checker);
+
+    let return_bound = function
+        .ret_type()
+        .and_then(|ret_type| ret_type.ty())
+        .and_then(|ty| ReturnBound::of(&ty));
+    // The origins of the elided lifetimes of the parameters
+    let mut elided = Vec::new();
+
+    let generics = Generics::of(function);
+    if let Some(params) = function.param_list() {
+        if let Some(self_param) = params.self_param() {
+            let local = sema.to_def(&self_param).unwrap();
+            let var = checker.create_param(
+                self_param.amp_token().is_none() && self_param.mut_token().is_some(),
+                is_copy(&local.ty(sema.db), &generics, sema.db),
+                "self".to_string(),
+                self_param.syntax().text_range(),
+            );
+            locals_map.insert(local, var);
+        }
+        for param in params.params() {
+            if let Some(ast::Pat::IdentPat(ident)) = param.pat() {
+                let local = sema.to_def(&ident).unwrap();
+                let var = checker.create_param(
+                    ident.mut_token().is_some(),
+                    is_copy(&local.ty(sema.db), &generics, sema.db),
+                    local.name(sema.db).unwrap().as_text().unwrap().to_string(),
+                    param.syntax().text_range(),
+                );
+                // Only the bound of the returned type tells which data the parameters may pass on to the caller
+                if let (Some(_), Some(ty)) = (&return_bound, param.ty()) {
+                    let mut carried = Vec::new();
+                    for lifetime in signature::param_lifetimes(&ty) {
+                        if lifetime == "'_" {
+                            let origin = checker
+                                .create_signature_origin(lifetime, param.syntax().text_range());
+                            elided.push(origin);
+                            carried.push(origin);
+                        } else if let Some(origin) = lifetimes.get(&lifetime) {
+                            carried.push(*origin);
+                        }
+                    }
+                    checker.carry_origins(var, carried);
+                }
+                locals_map.insert(local, var);
+            } else {
+                return Err(CheckError::unsupported(param.syntax()));
+            }
+        }
+    }
+
+    let body = function.body().unwrap();
+    let return_var = process_block(&body, checker, &mut locals_map, sema)?;
+    checker
+        .leave_function(Some(return_var), end_range(&body))
+        .map_err(CheckError::at(tail_range(&body)))?;
+
+    if let Some(return_bound) = return_bound {
+        let origins = |lifetime: &str| match lifetime {
+            "'_" => elided.clone(),
+            lifetime => lifetimes.get(lifetime).copied().into_iter().collect(),
+        };
+        match return_bound {
+            ReturnBound::Opaque { ty, captured } => {
+                let captured = captured
+                    .iter()
+                    .flat_map(|lifetime| origins(lifetime))
+                    .collect::<Vec<_>>();
+                checker.check_captures(return_var, &ty, &captured)
+            }
+            ReturnBound::Object { lifetime } => {
+                checker.check_lifetime_requirement(return_var, &origins(&lifetime), &lifetime)
+            }
+        }
+        .map_err(CheckError::at(tail_range(&body)))?;
+    }
 
     Ok(())
 }
 
+/// Creates the origins of the function's lifetime parameters and records the bounds between them
+/// (`<'a: 'b>` and `where 'a: 'b`). Returns the origins by name, including `'static`.
+fn declare_lifetimes(
+    function: &ast::Fn,
+    checker: &mut Checker,
+) -> HashMap<String, checker::OriginId> {
+    let mut origins = HashMap::new();
+    origins.insert("'static".to_string(), checker.static_origin());
+
+    let mut bounds = Vec::new();
+    if let Some(generics) = function.generic_param_list() {
+        for param in generics.lifetime_params() {
+            if let Some(lifetime) = param.lifetime() {
+                let origin = checker
+                    .create_signature_origin(lifetime.to_string(), param.syntax().text_range());
+                origins.insert(lifetime.to_string(), origin);
+                bounds.push((lifetime, param.type_bound_list()));
+            }
+        }
+    }
+    if let Some(where_clause) = function.where_clause() {
+        for predicate in where_clause.predicates() {
+            if let Some(lifetime) = predicate.lifetime() {
+                bounds.push((lifetime, predicate.type_bound_list()));
+            }
+        }
+    }
+
+    for (lifetime, bound_list) in bounds {
+        let longer = origins.get(&lifetime.to_string());
+        for bound in bound_list.iter().flat_map(|bound_list| bound_list.bounds()) {
+            let shorter = bound
+                .lifetime()
+                .and_then(|bound| origins.get(&bound.to_string()));
+            if let (Some(longer), Some(shorter)) = (longer, shorter) {
+                checker.add_outlives(*longer, *shorter);
+            }
+        }
+    }
+    origins
+}
+
 fn process_block<'db, DB: HirDatabase>(
     block: &ast::BlockExpr,
     checker: &mut Checker,
     locals_map: &mut HashMap<hir::Local, VarId>,
     sema: &Semantics<'db, DB>,
 ) -> Result<VarId, CheckError> {
-    checker.enter_scope();
+    checker.enter_scope(block.syntax().text_range());
 
     for stmt in block.stmt_list().unwrap().statements() {
         info!("Processing '{}'", stmt.syntax().text());
         process_statement(&stmt, checker, locals_map, sema)?;
         info!("\n{}", checker);
+        checker.snapshot(stmt.syntax().text_range());
     }
 
     let return_var = if let Some(expr) = block.tail_expr() {
-        Some(resolve_borrow_target(&expr, checker, locals_map, sema)?)
+        let return_var = resolve_borrow_target(&expr, checker, locals_map, sema)?;
+        checker.snapshot(expr.syntax().text_range());
+        Some(return_var)
     } else {
         None
     };
 
-    checker.leave_scope(return_var)?;
+    checker
+        .leave_scope(return_var, end_range(block))
+        .map_err(CheckError::at(tail_range(block)))?;
 
     Ok(return_var.unwrap_or_else(|| checker.void_literal()))
 }
 
+/// Checks a block guarded by the condition of an `if` or a `while`. The bindings of `if let` and `while let` live in
+/// a scope around the block. Chains of `let`s (e.g. `if let Some(a) = x && let Some(b) = a`) aren't supported.
+fn process_guarded_block<'db, DB: HirDatabase>(
+    condition: &ast::Condition,
+    block: &ast::BlockExpr,
+    checker: &mut Checker,
+    locals_map: &mut HashMap<hir::Local, VarId>,
+    sema: &Semantics<'db, DB>,
+) -> Result<VarId, CheckError> {
+    let lets = condition
+        .syntax()
+        .descendants_with_tokens()
+        .filter(|element| element.kind() == SyntaxKind::LET_KW)
+        .count();
+    if lets > 1 || (lets == 1 && condition.pat().is_none()) {
+        return Err(CheckError::unsupported(condition.syntax()));
+    }
+    let scrutinee = resolve_borrow_target(&condition.expr().unwrap(), checker, locals_map, sema)?;
+    let pat = match condition.pat() {
+        Some(pat) => pat,
+        None => return process_block(block, checker, locals_map, sema),
+    };
+
+    let range = condition.syntax().text_range();
+    checker.enter_scope(range.cover(block.syntax().text_range()));
+    declare_bindings(&pat, checker, locals_map, sema);
+    bind_pattern(
+        &pat,
+        scrutinee,
+        BindingMode::Move,
+        false,
+        range,
+        checker,
+        locals_map,
+        sema,
+    )?;
+    let value = process_block(block, checker, locals_map, sema)?;
+    checker
+        .leave_scope(Some(value), end_range(block))
+        .map_err(CheckError::at(tail_range(block)))?;
+    Ok(value)
+}
+
 fn process_statement<'db, DB: HirDatabase>(
     stmt: &ast::Stmt,
     checker: &mut Checker,
     locals_map: &mut HashMap<hir::Local, VarId>,
     sema: &Semantics<'db, DB>,
 ) -> Result<(), CheckError> {
+    checker.enter_statement();
     match stmt {
         ast::Stmt::ExprStmt(expr) => {
             let expr = expr.expr().unwrap();
             let _ = resolve_borrow_target(&expr, checker, locals_map, sema)?;
         }
         ast::Stmt::LetStmt(let_stmt) => {
-            if let ast::Pat::IdentPat(ident) = let_stmt.pat().unwrap() {
-                let new_local = sema.to_def(&ident).unwrap();
-                /*
-                let is_copy = sema
-                    .type_of_pat(&ident.pat().unwrap())
-                    .unwrap()
-                    .original()
-                    .is_copy(sema.db);
-                    */
-                let new_var = checker.create_var(
-                    ident.mut_token().is_some(),
-                    false, // TODO
-                    new_local
-                        .name(sema.db)
-                        .unwrap()
-                        .as_text()
-                        .unwrap()
-                        .to_string(),
-                );
-                locals_map.insert(new_local, new_var);
+            let pat = let_stmt.pat().unwrap();
+            let range = let_stmt.syntax().text_range();
+            declare_bindings(&pat, checker, locals_map, sema);
+
+            if let Some(init) = let_stmt.initializer() {
+                let value = resolve_borrow_target(&init, checker, locals_map, sema)?;
+                // `let ref x = temporary();` extends the temporary like `let x = &temporary();` does
+                checker.extend_temporary(value);
 
-                if let Some(init) = let_stmt.initializer() {
-                    let rhs = resolve_borrow_target(&init, checker, locals_map, sema)?;
-                    checker.initialize_var_with_value(new_var, vec![rhs])?;
+                // The `else` block of `let ... else` runs instead of the bindings' uses, so it must not fall through
+                if let Some(block) = let_stmt
+                    .let_else()
+                    .and_then(|let_else| let_else.block_expr())
+                {
+                    let diverges = sema
+                        .type_of_expr(&ast::Expr::BlockExpr(block.clone()))
+                        .map_or(false, |ty| {
+                            let ty = ty.original();
+                            !ty.is_unknown() && ty.display(sema.db).to_string() == "!"
+                        });
+                    // The moves and borrows of the `else` block don't happen on the path that continues after the
+                    // `let`
+                    let state = checker.save_state();
+                    let _ = process_block(&block, checker, locals_map, sema)?;
+                    checker.restore_state(state);
+                    if !diverges {
+                        return Err(CheckError::at(block.syntax().text_range())(
+                            CheckerError::ElseNotDiverging,
+                        ));
+                    }
                 }
+
+                bind_pattern(
+                    &pat,
+                    value,
+                    BindingMode::Move,
+                    false,
+                    range,
+                    checker,
+                    locals_map,
+                    sema,
+                )?;
+            }
+        }
+        // Items don't hold borrows of the body, nested functions are checked on their own
+        ast::Stmt::Item(_) => {}
+    }
+    // Temporaries are dropped at the statement's semicolon
+    let end = stmt.syntax().text_range().end();
+    checker.leave_statement(TextRange::new(end - TextSize::from(1), end));
+    Ok(())
+}
+
+/// The default binding mode of a pattern, which bindings without `ref` or `mut` use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingMode {
+    /// The bindings take the matched value
+    Move,
+    /// A non-reference pattern matched a reference: the bindings reborrow the value behind it, so they hold the
+    /// borrows of the reference
+    Reborrow,
+}
+
+/// Creates the vars of the bindings in `pat`, uninitialized
+fn declare_bindings<'db, DB: HirDatabase>(
+    pat: &ast::Pat,
+    checker: &mut Checker,
+    locals_map: &mut HashMap<hir::Local, VarId>,
+    sema: &Semantics<'db, DB>,
+) {
+    let generics = Generics::enclosing(pat.syntax());
+    for ident in pat.syntax().descendants().filter_map(ast::IdentPat::cast) {
+        // Identifiers resolving to constants or unit variants (e.g. `None`) don't bind anything
+        let local = match sema.to_def(&ident) {
+            Some(local) => local,
+            None => continue,
+        };
+        rename_shadowed(&ident, local, checker, locals_map, sema);
+        let var = checker.create_var(
+            ident.mut_token().is_some(),
+            is_copy(&local.ty(sema.db), &generics, sema.db),
+            local.name(sema.db).unwrap().as_text().unwrap().to_string(),
+        );
+        locals_map.insert(local, var);
+    }
+}
+
+/// Whether values of `ty` are copied rather than moved. Without a sysroot there is no `Copy` trait, so the builtin
+/// scalars, shared references, type parameters bounded by `Copy` (in `generics`) and tuples of those are known to be
+/// `Copy` regardless.
+fn is_copy(ty: &hir::Type, generics: &Generics, db: &dyn HirDatabase) -> bool {
+    ty.is_copy(db)
+        || ty.as_builtin().is_some()
+        || generics.is_copy(&ty.display(db).to_string())
+        || (ty.is_reference() && !ty.is_mutable_reference())
+        || ty.is_unit()
+        || {
+            let fields = ty.tuple_fields(db);
+            !fields.is_empty() && fields.iter().all(|field| is_copy(field, generics, db))
+        }
+}
+
+/// The var of the value of type `pointee` behind the reference `target`. Values of unknown types, e.g. behind a
+/// `Box`, are assumed to be `Copy`, so moving them out isn't reported.
+fn deref_var<'db, DB: HirDatabase>(
+    target: VarId,
+    pointee: Option<hir::Type>,
+    node: &SyntaxNode,
+    checker: &mut Checker,
+    sema: &Semantics<'db, DB>,
+) -> VarId {
+    let is_copy = pointee.map_or(true, |ty| is_copy(&ty, &Generics::enclosing(node), sema.db));
+    checker.get_deref_var(target, is_copy)
+}
+
+/// Renames the vars of the locals of the same name that the binding `ident` shadows, so that diagnostics tell them
+/// apart, e.g. "x (shadowed, declared on line 3)"
+fn rename_shadowed<'db, DB: HirDatabase>(
+    ident: &ast::IdentPat,
+    local: hir::Local,
+    checker: &mut Checker,
+    locals_map: &HashMap<hir::Local, VarId>,
+    sema: &Semantics<'db, DB>,
+) {
+    let name = local.name(sema.db);
+    let range = ident.syntax().text_range();
+    let source = sema
+        .db
+        .file_text(sema.original_range(ident.syntax()).file_id);
+    for (other, var) in locals_map {
+        if *other == local || other.name(sema.db) != name {
+            continue;
+        }
+        let declaration = other.source(sema.db).value.either(
+            |pat| pat.syntax().clone(),
+            |self_param| self_param.syntax().clone(),
+        );
+        // The other local is in scope if the block, condition or function declaring it contains the binding
+        let in_scope = declaration
+            .ancestors()
+            .find(|node| {
+                ast::BlockExpr::can_cast(node.kind())
+                    || ast::IfExpr::can_cast(node.kind())
+                    || ast::WhileExpr::can_cast(node.kind())
+                    || ast::Fn::can_cast(node.kind())
+            })
+            .map_or(false, |scope| scope.text_range().contains_range(range));
+        if in_scope && declaration.text_range().start() < range.start() {
+            checker.rename_var(
+                *var,
+                shadowed_identifier(
+                    &local.name(sema.db).unwrap().as_text().unwrap(),
+                    line_of(&source, declaration.text_range()),
+                ),
+            );
+        }
+    }
+}
+
+/// Initializes the bindings of `pat`, which matches the value of `place`. A binding of the whole value takes it, the
+/// bindings of its parts (`is_part`) take a projection of it like a field access does. `ref` bindings borrow all of
+/// `place`.
+#[allow(clippy::too_many_arguments)]
+fn bind_pattern<'db, DB: HirDatabase>(
+    pat: &ast::Pat,
+    place: VarId,
+    mode: BindingMode,
+    is_part: bool,
+    range: TextRange,
+    checker: &mut Checker,
+    locals_map: &mut HashMap<hir::Local, VarId>,
+    sema: &Semantics<'db, DB>,
+) -> Result<(), CheckError> {
+    match pat {
+        ast::Pat::IdentPat(ident) => {
+            let var = match sema.to_def(ident) {
+                Some(local) => *locals_map.get(&local).unwrap(),
+                None => return Ok(()),
+            };
+            // `ref` and `mut` override the default binding mode, they bind the value behind the matched reference
+            let overrides = ident.ref_token().is_some() || ident.mut_token().is_some();
+            let bound = if mode == BindingMode::Reborrow && overrides {
+                let pointee = sema
+                    .type_of_pat(&ast::Pat::IdentPat(ident.clone()))
+                    .map(|ty| ty.original());
+                deref_var(place, pointee, ident.syntax(), checker, sema)
             } else {
-                todo!();
+                place
+            };
+            match ident.ref_token() {
+                Some(_) => checker.initialize_var_with_borrow(
+                    var,
+                    vec![bound],
+                    ident.mut_token().is_some(),
+                    range,
+                ),
+                None if !is_part => checker.initialize_var_with_value(var, vec![bound], range),
+                None => {
+                    let tmp = checker.create_expr_tmp(format!("<field> {}", ident.syntax()));
+                    checker
+                        .initialize_var_with_projection(tmp, bound, None, range)
+                        .and_then(|_| checker.initialize_var_with_value(var, vec![tmp], range))
+                }
+            }
+            .map_err(CheckError::at(range))?;
+
+            // `x @ pat` binds the same value twice
+            if let Some(pat) = ident.pat() {
+                bind_pattern(&pat, place, mode, true, range, checker, locals_map, sema)?;
+            }
+        }
+        // `&pat` matches the value behind the reference
+        ast::Pat::RefPat(pat) => {
+            let pointee = sema
+                .type_of_pat(&ast::Pat::RefPat(pat.clone()))
+                .and_then(|ty| ty.original().remove_ref());
+            let deref = deref_var(place, pointee, pat.syntax(), checker, sema);
+            if let Some(pat) = pat.pat() {
+                bind_pattern(
+                    &pat,
+                    deref,
+                    BindingMode::Move,
+                    true,
+                    range,
+                    checker,
+                    locals_map,
+                    sema,
+                )?;
+            }
+        }
+        ast::Pat::ParenPat(pat) => {
+            if let Some(pat) = pat.pat() {
+                bind_pattern(&pat, place, mode, is_part, range, checker, locals_map, sema)?;
+            }
+        }
+        ast::Pat::OrPat(pat) => {
+            for pat in pat.pats() {
+                bind_pattern(&pat, place, mode, is_part, range, checker, locals_map, sema)?;
+            }
+        }
+        _ => {
+            // A non-reference pattern matching a reference matches the value behind it ("default binding modes")
+            let matches_reference = sema
+                .type_of_pat(pat)
+                .map_or(false, |ty| ty.has_adjustment());
+            let mode = if matches_reference {
+                BindingMode::Reborrow
+            } else {
+                mode
+            };
+
+            let parts: Vec<ast::Pat> = match pat {
+                ast::Pat::TupleStructPat(pat) => pat.fields().collect(),
+                ast::Pat::TuplePat(pat) => pat.fields().collect(),
+                ast::Pat::SlicePat(pat) => pat.pats().collect(),
+                ast::Pat::BoxPat(pat) => pat.pat().into_iter().collect(),
+                ast::Pat::RecordPat(pat) => pat
+                    .record_pat_field_list()
+                    .into_iter()
+                    .flat_map(|fields| fields.fields())
+                    .filter_map(|field| field.pat())
+                    .collect(),
+                // Literals, ranges, paths and wildcards don't bind anything
+                _ => Vec::new(),
+            };
+            for part in parts {
+                bind_pattern(&part, place, mode, true, range, checker, locals_map, sema)?;
             }
         }
-        ast::Stmt::Item(_) => todo!(),
     }
     Ok(())
 }
@@ -143,13 +827,41 @@ fn resolve_borrow_target<'db, DB: HirDatabase>(
     sema: &Semantics<'db, DB>,
 ) -> Result<VarId, CheckError> {
     match expr {
+        // A string literal is a `&'static str` borrowing its promoted data
+        ast::Expr::Literal(literal)
+            if matches!(
+                literal.kind(),
+                ast::LiteralKind::String(_) | ast::LiteralKind::ByteString(_)
+            ) =>
+        {
+            let text = literal.syntax().text().to_string();
+            let tmp = checker.create_ref_tmp(false, text.clone());
+            let data = checker.create_literal(text);
+            checker
+                .initialize_var_with_borrow(tmp, vec![data], false, literal.syntax().text_range())
+                .map_err(CheckError::at(literal.syntax().text_range()))?;
+            Ok(tmp)
+        }
         ast::Expr::Literal(literal) => {
             Ok(checker.create_literal(literal.syntax().text().to_string()))
         }
         ast::Expr::PathExpr(path) => {
-            let local = resolve_local_ref(path.path().unwrap(), sema).unwrap();
+            let text = path.syntax().text().to_string();
+            let local = match sema.resolve_path(&path.path().unwrap()) {
+                Some(hir::PathResolution::Local(local)) => local,
+                Some(hir::PathResolution::Def(hir::ModuleDef::Static(item))) => {
+                    return Ok(checker.static_var(item, item.is_mut(sema.db), text))
+                }
+                Some(hir::PathResolution::Def(hir::ModuleDef::Const(_))) => {
+                    return Ok(checker.create_static(false, text))
+                }
+                // Other items (e.g. unit structs and variants) are constants that don't hold borrows
+                _ => return Ok(checker.create_literal(text)),
+            };
             let var = *locals_map.get(&local).unwrap();
-            checker.check_var_usable(var)?;
+            checker
+                .read_var(var, path.syntax().text_range())
+                .map_err(CheckError::at(path.syntax().text_range()))?;
             Ok(var)
         }
         ast::Expr::RefExpr(subexpr) => {
@@ -157,40 +869,75 @@ fn resolve_borrow_target<'db, DB: HirDatabase>(
 
             let tmp = checker.create_ref_tmp(is_mut_borrow, subexpr.syntax().text().to_string()); // Dunno if it is correct that a tmp var created by an immutable borrow is immutable
 
-            let target =
-                resolve_borrow_target(&subexpr.expr().unwrap(), checker, locals_map, sema)?;
-            checker.initialize_var_with_borrow(tmp, vec![target], is_mut_borrow)?;
+            let target = resolve_borrowed(&subexpr.expr().unwrap(), checker, locals_map, sema)?;
+            if is_lifetime_extended(subexpr) {
+                checker.extend_temporary(target);
+            }
+            checker
+                .initialize_var_with_borrow(
+                    tmp,
+                    vec![target],
+                    is_mut_borrow,
+                    subexpr.syntax().text_range(),
+                )
+                .map_err(CheckError::at(subexpr.syntax().text_range()))?;
 
             Ok(tmp)
         }
         ast::Expr::PrefixExpr(prefix_expr) => {
-            if prefix_expr.op_kind().unwrap() != ast::UnaryOp::Deref {
-                todo!();
+            if prefix_expr.op_kind() != Some(ast::UnaryOp::Deref) {
+                return Err(CheckError::unsupported(prefix_expr.syntax()));
             }
-            let target =
-                resolve_borrow_target(&prefix_expr.expr().unwrap(), checker, locals_map, sema)?;
+            let operand = prefix_expr.expr().unwrap();
+            let target = resolve_borrow_target(&operand, checker, locals_map, sema)?;
 
-            Ok(checker.get_deref_var(target))
+            let pointee = sema
+                .type_of_expr(&operand)
+                .and_then(|ty| ty.original().remove_ref());
+            Ok(deref_var(
+                target,
+                pointee,
+                prefix_expr.syntax(),
+                checker,
+                sema,
+            ))
         }
         ast::Expr::BinExpr(bin_expr) => {
-            let lhs = resolve_borrow_target(&bin_expr.lhs().unwrap(), checker, locals_map, sema)?;
+            let op = match bin_expr.op_kind() {
+                Some(ast::BinaryOp::Assignment { op }) => op,
+                _ => return Err(CheckError::unsupported(bin_expr.syntax())),
+            };
+            let lhs_expr = bin_expr.lhs().unwrap();
+            let lhs = if op.is_none() {
+                resolve_assignee(&lhs_expr, checker, locals_map, sema)?
+            } else {
+                resolve_borrow_target(&lhs_expr, checker, locals_map, sema)?
+            };
             let rhs = resolve_borrow_target(&bin_expr.rhs().unwrap(), checker, locals_map, sema)?;
-            match bin_expr.op_kind().unwrap() {
-                ast::BinaryOp::LogicOp(_) => todo!(),
-                ast::BinaryOp::ArithOp(_) => todo!(),
-                ast::BinaryOp::CmpOp(_) => todo!(),
-                ast::BinaryOp::Assignment { op } => {
-                    checker.initialize_var_with_value(lhs, vec![rhs])?;
-                    // An assignment returns a new var of type void
-                    Ok(checker.void_literal())
+            let range = bin_expr.syntax().text_range();
+            if let (None, ast::Expr::FieldExpr(_)) = (op, &lhs_expr) {
+                checker.assign_field(lhs, rhs, range)
+            } else {
+                checker.initialize_var_with_value(lhs, vec![rhs], range)
+            }
+            .map_err(CheckError::at(range))?;
+            // A `static mut` outlives the function, so the value assigned to it (or to its fields) can't borrow its
+            // locals
+            let mut base = lhs_expr.clone();
+            while let ast::Expr::FieldExpr(field) = base.clone() {
+                base = field.expr().unwrap();
+            }
+            if let ast::Expr::PathExpr(path) = &base {
+                if resolve_local_ref(path.path().unwrap(), sema)?.is_none() {
+                    require_static(lhs, range, checker)?;
                 }
             }
+            // An assignment returns a new var of type void
+            Ok(checker.void_literal())
         }
         ast::Expr::IfExpr(expr) => {
-            let cond = expr.condition().unwrap().expr().unwrap();
-            let _ = resolve_borrow_target(&cond, checker, locals_map, sema)?;
-
-            let mut vars = vec![process_block(
+            let mut vars = vec![process_guarded_block(
+                &expr.condition().unwrap(),
                 &expr.then_branch().unwrap(),
                 checker,
                 locals_map,
@@ -204,7 +951,8 @@ fn resolve_borrow_target<'db, DB: HirDatabase>(
                         break;
                     }
                     ast::ElseBranch::IfExpr(expr) => {
-                        vars.push(process_block(
+                        vars.push(process_guarded_block(
+                            &expr.condition().unwrap(),
                             &expr.then_branch().unwrap(),
                             checker,
                             locals_map,
@@ -214,27 +962,558 @@ fn resolve_borrow_target<'db, DB: HirDatabase>(
                     }
                 }
             }
-            let expr_value_var = checker.create_var(false, false, "<if rslt>".to_string());
-            checker.initialize_var_with_value(expr_value_var, vars)?;
+            let expr_value_var = checker.create_expr_tmp("<if rslt>".to_string());
+            checker
+                .initialize_var_with_value(expr_value_var, vars, expr.syntax().text_range())
+                .map_err(CheckError::at(expr.syntax().text_range()))?;
 
             Ok(expr_value_var)
         }
-        _ => todo!(),
+        // What the body holds on to may still be borrowed in the next iteration, so it is checked twice
+        ast::Expr::WhileExpr(expr) => {
+            for _ in 0..2 {
+                let _ = process_guarded_block(
+                    &expr.condition().unwrap(),
+                    &expr.loop_body().unwrap(),
+                    checker,
+                    locals_map,
+                    sema,
+                )?;
+            }
+            Ok(checker.void_literal())
+        }
+        // The returned value escapes to the caller, like the value of the function's body
+        ast::Expr::ReturnExpr(expr) => {
+            let value = match expr.expr() {
+                Some(value) => resolve_borrow_target(&value, checker, locals_map, sema)?,
+                None => checker.void_literal(),
+            };
+            checker
+                .check_returned(value)
+                .map_err(CheckError::at(expr.syntax().text_range()))?;
+            Ok(checker.void_literal())
+        }
+        ast::Expr::BlockExpr(block) => process_block(block, checker, locals_map, sema),
+        ast::Expr::ClosureExpr(closure) => {
+            let range = closure.syntax().text_range();
+
+            // The closure holds a reference to every place it borrows and the values it moves. A borrowed field
+            // (e.g. `x.a`) is borrowed on its own, so closures capturing disjoint fields don't conflict.
+            let mut captured = Vec::new();
+            for capture in captures::closure_captures(closure, sema) {
+                // Parameters and locals of the closure itself aren't captured
+                let var = match locals_map.get(&capture.local) {
+                    Some(var) => *var,
+                    None => continue,
+                };
+                if capture.mode == CaptureMode::ByValue {
+                    captured.push(var);
+                } else {
+                    let is_mut = capture.mode == CaptureMode::Unique;
+                    let place = match capture.place.split_once('.') {
+                        Some((_, fields)) => checker.get_field_var(var, fields),
+                        None => var,
+                    };
+                    let tmp = checker.create_ref_tmp(
+                        is_mut,
+                        format!("&{}{}", if is_mut { "mut " } else { "" }, capture.place),
+                    );
+                    checker
+                        .initialize_var_with_borrow(tmp, vec![place], is_mut, range)
+                        .map_err(CheckError::at(capture.range))?;
+                    captured.push(tmp);
+                }
+            }
+            if captured.is_empty() {
+                captured.push(checker.void_literal());
+            }
+
+            let closure_var = checker.create_expr_tmp("<closure>".to_string());
+            checker
+                .initialize_var_with_value(closure_var, captured, range)
+                .map_err(CheckError::at(range))?;
+
+            Ok(closure_var)
+        }
+        ast::Expr::RecordExpr(record) => {
+            let path = record.path().unwrap();
+            let adt = AdtFields::resolve(&path, sema);
+
+            let mut fields = Vec::new();
+            if let Some(field_list) = record.record_expr_field_list() {
+                for field in field_list.fields() {
+                    let value =
+                        resolve_borrow_target(&field.expr().unwrap(), checker, locals_map, sema)?;
+                    let lifetimes = adt
+                        .as_ref()
+                        .zip(field.field_name())
+                        .and_then(|(adt, name)| adt.named(&name.to_string()));
+                    fields.push((value, lifetimes));
+                }
+                // The fields taken from the base (`..base`) may hold any of its borrows
+                if let Some(base) = field_list.spread() {
+                    fields.push((
+                        resolve_borrow_target(&base, checker, locals_map, sema)?,
+                        None,
+                    ));
+                }
+            }
+
+            construct(&path, fields, record.syntax().text_range(), checker)
+        }
+        ast::Expr::FieldExpr(field) => {
+            let range = field.syntax().text_range();
+            let base = resolve_borrow_target(&field.expr().unwrap(), checker, locals_map, sema)?;
+
+            // The field takes the borrows its declared type can hold. The fields of tuples have no declaration,
+            // they take every borrow of the base.
+            let tmp = checker.create_expr_tmp(format!("<field> {}", field.syntax()));
+            checker
+                .initialize_var_with_projection(
+                    tmp,
+                    base,
+                    fields::field_lifetimes(field, sema),
+                    range,
+                )
+                .map_err(CheckError::at(range))?;
+
+            Ok(tmp)
+        }
+        ast::Expr::CallExpr(call) => {
+            let callee = call.expr().unwrap();
+
+            // Tuple structs and variants hold their arguments as fields
+            if let ast::Expr::PathExpr(path) = &callee {
+                let path = path.path().unwrap();
+                if let Some(adt) = AdtFields::resolve(&path, sema) {
+                    let mut fields = Vec::new();
+                    for (index, arg) in call.arg_list().unwrap().args().enumerate() {
+                        let value = resolve_borrow_target(&arg, checker, locals_map, sema)?;
+                        fields.push((value, adt.positional(index)));
+                    }
+                    return construct(&path, fields, call.syntax().text_range(), checker);
+                }
+            }
+
+            let mut function = None;
+            match &callee {
+                ast::Expr::PathExpr(path) => match sema.resolve_path(&path.path().unwrap()) {
+                    Some(hir::PathResolution::Def(hir::ModuleDef::Function(callee))) => {
+                        function = Some(callee)
+                    }
+                    // Calling a local closure uses it, and with it everything it captured
+                    Some(hir::PathResolution::Local(_)) => {
+                        let _ = resolve_borrow_target(&callee, checker, locals_map, sema)?;
+                    }
+                    // Other items (e.g. unresolved functions) don't hold a var
+                    _ => {}
+                },
+                _ => {
+                    let _ = resolve_borrow_target(&callee, checker, locals_map, sema)?;
+                }
+            }
+
+            let mut args = Vec::new();
+            for arg in call.arg_list().unwrap().args() {
+                args.push(resolve_borrow_target(&arg, checker, locals_map, sema)?);
+            }
+
+            call_result(function, args, call.syntax().text_range(), checker, sema)
+        }
+        ast::Expr::MethodCallExpr(call) => {
+            let range = call.syntax().text_range();
+            let receiver = call.receiver().unwrap();
+            let function = sema.resolve_method_call(call);
+            let access = function
+                .and_then(|function| function.self_param(sema.db))
+                .map(|self_param| self_param.access(sema.db));
+
+            // The receiver is auto-ref'd, or reborrowed if it is a reference already. Unresolved methods are
+            // assumed to take `&self`. A mutable auto-ref is a two-phase borrow: it is only activated at the
+            // call, so the arguments may still read the receiver (`v.push(v.len())`).
+            let mut reservation = None;
+            let self_arg = if let Some(hir::Access::Owned) = access {
+                resolve_borrow_target(&receiver, checker, locals_map, sema)?
+            } else {
+                let is_mut = matches!(access, Some(hir::Access::Exclusive));
+                let mut target = resolve_borrowed(&receiver, checker, locals_map, sema)?;
+                if let Some(pointee) = sema
+                    .type_of_expr(&receiver)
+                    .and_then(|ty| ty.original().remove_ref())
+                {
+                    target = deref_var(target, Some(pointee), receiver.syntax(), checker, sema);
+                }
+
+                let tmp = checker.create_ref_tmp(
+                    is_mut,
+                    format!("&{}{}", if is_mut { "mut " } else { "" }, receiver.syntax()),
+                );
+                if is_mut {
+                    checker
+                        .initialize_var_with_reservation(tmp, target, range)
+                        .map_err(CheckError::at(range))?;
+                    reservation = Some(tmp);
+                } else {
+                    checker
+                        .initialize_var_with_borrow(tmp, vec![target], false, range)
+                        .map_err(CheckError::at(range))?;
+                }
+                tmp
+            };
+
+            let mut args = vec![self_arg];
+            for arg in call.arg_list().unwrap().args() {
+                args.push(resolve_borrow_target(&arg, checker, locals_map, sema)?);
+            }
+            if let Some(reservation) = reservation {
+                checker.activate_reservation(reservation, range);
+            }
+
+            call_result(function, args, range, checker, sema)
+        }
+        _ => Err(CheckError::unsupported(expr.syntax())),
+    }
+}
+
+/// Whether the borrowed temporary lives as long as the `let` statement's block, e.g. `let x = &temporary();`.
+/// This is the case for borrows that are, possibly nested in other borrows, tuples, arrays, struct literals, casts
+/// or the tail expressions of blocks, the initializer of a `let`.
+fn is_lifetime_extended(ref_expr: &ast::RefExpr) -> bool {
+    let mut node = ref_expr.syntax().clone();
+    while let Some(parent) = node.parent() {
+        if let Some(let_stmt) = ast::LetStmt::cast(parent.clone()) {
+            return let_stmt
+                .initializer()
+                .map_or(false, |init| *init.syntax() == node);
+        }
+        let is_extending = match ast::StmtList::cast(parent.clone()) {
+            Some(stmt_list) => stmt_list
+                .tail_expr()
+                .map_or(false, |tail| *tail.syntax() == node),
+            None => {
+                ast::RefExpr::can_cast(parent.kind())
+                    || ast::ParenExpr::can_cast(parent.kind())
+                    || ast::TupleExpr::can_cast(parent.kind())
+                    || ast::ArrayExpr::can_cast(parent.kind())
+                    || ast::CastExpr::can_cast(parent.kind())
+                    || ast::RecordExprField::can_cast(parent.kind())
+                    || ast::RecordExprFieldList::can_cast(parent.kind())
+                    || ast::RecordExpr::can_cast(parent.kind())
+                    || ast::BlockExpr::can_cast(parent.kind())
+            }
+        };
+        if !is_extending {
+            return false;
+        }
+        node = parent;
     }
+    false
 }
 
+/// Resolves an expression that is borrowed. Borrowing a local doesn't read it, the borrow is checked against the
+/// local's other borrows instead.
+fn resolve_borrowed<'db, DB: HirDatabase>(
+    expr: &ast::Expr,
+    checker: &mut Checker,
+    locals_map: &mut HashMap<hir::Local, VarId>,
+    sema: &Semantics<'db, DB>,
+) -> Result<VarId, CheckError> {
+    match expr {
+        ast::Expr::PathExpr(path) => {
+            let local = match resolve_local_ref(path.path().unwrap(), sema)? {
+                Some(local) => local,
+                // Borrowing a `static` or `const` item borrows its var in the static origin
+                None => return resolve_borrow_target(expr, checker, locals_map, sema),
+            };
+            let var = *locals_map.get(&local).unwrap();
+            checker
+                .check_var_usable(var, path.syntax().text_range())
+                .map_err(CheckError::at(path.syntax().text_range()))?;
+            Ok(var)
+        }
+        // Borrowing a field borrows the whole base, so borrows of disjoint fields conflict. If the base is a
+        // reference, the value behind it is borrowed.
+        ast::Expr::FieldExpr(field) => {
+            let base = field.expr().unwrap();
+            let target = resolve_borrowed(&base, checker, locals_map, sema)?;
+            match sema
+                .type_of_expr(&base)
+                .and_then(|ty| ty.original().remove_ref())
+            {
+                Some(pointee) => Ok(deref_var(
+                    target,
+                    Some(pointee),
+                    base.syntax(),
+                    checker,
+                    sema,
+                )),
+                None => Ok(target),
+            }
+        }
+        _ => resolve_borrow_target(expr, checker, locals_map, sema),
+    }
+}
+
+/// The value of a struct or enum variant, into which the fields are moved
+fn construct(
+    path: &ast::Path,
+    mut fields: Vec<(VarId, Option<Vec<String>>)>,
+    range: TextRange,
+    checker: &mut Checker,
+) -> Result<VarId, CheckError> {
+    if fields.is_empty() {
+        fields.push((checker.void_literal(), Some(Vec::new())));
+    }
+
+    let var = checker.create_expr_tmp(format!("<{}>", path));
+    checker
+        .initialize_var_with_fields(var, fields, range)
+        .map_err(CheckError::at(range))?;
+
+    Ok(var)
+}
+
+/// The value of a call, into which the arguments are moved. It holds the borrows of the arguments whose types share
+/// a generic parameter with the return type (see `Signature::returned_args`), or of all arguments if the callee is
+/// unknown. The other arguments are consumed by the call.
+fn call_result<'db, DB: HirDatabase>(
+    function: Option<hir::Function>,
+    args: Vec<VarId>,
+    range: TextRange,
+    checker: &mut Checker,
+    sema: &Semantics<'db, DB>,
+) -> Result<VarId, CheckError> {
+    let signature = function.and_then(|function| Signature::of(function, sema));
+
+    // Arguments for `'static` parameters must not borrow anything that the function drops
+    for index in signature
+        .iter()
+        .flat_map(|signature| signature.static_args())
+    {
+        if let Some(arg) = args.get(index) {
+            require_static(*arg, range, checker)?;
+        }
+    }
+
+    // The callee may store arguments in the places borrowed by the others, where its signature is invariant
+    for (into, from) in signature
+        .iter()
+        .flat_map(|signature| signature.invariant_flows(sema))
+    {
+        if let (Some(into), Some(from)) = (args.get(into), args.get(from)) {
+            checker
+                .store_through(*into, *from, range)
+                .map_err(CheckError::at(range))?;
+        }
+    }
+
+    let returned_args = signature.and_then(|signature| signature.returned_args());
+    let (returned, consumed): (Vec<_>, Vec<_>) =
+        args.into_iter().enumerate().partition(|(index, _)| {
+            returned_args
+                .as_ref()
+                .map_or(true, |returned_args| returned_args.contains(index))
+        });
+
+    if !consumed.is_empty() {
+        let consumed_var = checker.create_expr_tmp("<call args>".to_string());
+        checker
+            .initialize_var_with_value(
+                consumed_var,
+                consumed.into_iter().map(|(_, arg)| arg).collect(),
+                range,
+            )
+            .map_err(CheckError::at(range))?;
+    }
+
+    let mut returned = returned.into_iter().map(|(_, arg)| arg).collect::<Vec<_>>();
+    if returned.is_empty() {
+        returned.push(checker.void_literal());
+    }
+    let result = checker.create_expr_tmp("<call rslt>".to_string());
+    checker
+        .initialize_var_with_value(result, returned, range)
+        .map_err(CheckError::at(range))?;
+
+    Ok(result)
+}
+
+/// Checks that `var` doesn't borrow anything the function drops. The error is reported where the offending borrow
+/// has been created if it is known, at `range` otherwise.
+fn require_static(var: VarId, range: TextRange, checker: &Checker) -> Result<(), CheckError> {
+    checker.require_static(var).map_err(|error| {
+        let borrowed_at = match &error {
+            CheckerError::InvalidOrigin(_, borrowed, _) => checker.borrowed_at(var, *borrowed),
+            _ => None,
+        };
+        CheckError::at(borrowed_at.unwrap_or(range))(error)
+    })
+}
+
+/// Resolves the left hand side of an assignment, which doesn't need to be initialized. Assigning to a field writes to
+/// its base, or to the value behind the base if it is a reference.
+fn resolve_assignee<'db, DB: HirDatabase>(
+    expr: &ast::Expr,
+    checker: &mut Checker,
+    locals_map: &mut HashMap<hir::Local, VarId>,
+    sema: &Semantics<'db, DB>,
+) -> Result<VarId, CheckError> {
+    match expr {
+        ast::Expr::PathExpr(path) => match resolve_local_ref(path.path().unwrap(), sema)? {
+            Some(local) => Ok(*locals_map.get(&local).unwrap()),
+            // A `static mut` item
+            None => resolve_borrow_target(expr, checker, locals_map, sema),
+        },
+        ast::Expr::FieldExpr(_) => resolve_borrowed(expr, checker, locals_map, sema),
+        _ => resolve_borrow_target(expr, checker, locals_map, sema),
+    }
+}
+
+/// The range of a block's tail expression, which is where escaping values are reported
+fn tail_range(block: &ast::BlockExpr) -> TextRange {
+    block.tail_expr().map_or_else(
+        || block.syntax().text_range(),
+        |tail| tail.syntax().text_range(),
+    )
+}
+
+/// The range of a block's closing brace, which is where its locals are dropped
+fn end_range(block: &ast::BlockExpr) -> TextRange {
+    let end = block.syntax().text_range().end();
+    TextRange::new(end - TextSize::from(1), end)
+}
+
+fn line_of(source: &str, range: TextRange) -> usize {
+    source[..usize::from(range.start())].matches('\n').count() + 1
+}
+
+/// The local `path` refers to, if it isn't an item
 fn resolve_local_ref<'db, DB: HirDatabase>(
     path: ast::Path,
     sema: &Semantics<'db, DB>,
-) -> Option<hir::Local> {
-    match sema.resolve_path(&path).unwrap() {
-        hir::PathResolution::Local(local) => Some(local),
-        _ => None,
+) -> Result<Option<hir::Local>, CheckError> {
+    match sema.resolve_path(&path) {
+        Some(hir::PathResolution::Local(local)) => Ok(Some(local)),
+        Some(_) => Ok(None),
+        None => Err(CheckError::unsupported(path.syntax())),
     }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum CheckError {
+    #[error("{error}")]
+    Borrowcheck {
+        error: CheckerError,
+        range: TextRange,
+    },
+
     #[error(transparent)]
-    Borrowcheck(#[from] CheckerError),
+    Polonius(#[from] PoloniusError),
+
+    #[error("failed to load workspace: {0}")]
+    Workspace(String),
+
+    /// The checker panicked on a file of a workspace, the other files have been checked
+    #[error("the checker panicked: {0}")]
+    Panicked(String),
+
+    /// Syntax the checker doesn't model yet
+    #[error("unsupported syntax: `{syntax}`")]
+    Unsupported { syntax: String, range: TextRange },
+}
+
+impl CheckError {
+    /// The code of the matching rustc error (e.g. `E0499`)
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            CheckError::Borrowcheck { error, .. } => error.code(),
+            CheckError::Polonius(_)
+            | CheckError::Workspace(_)
+            | CheckError::Panicked(_)
+            | CheckError::Unsupported { .. } => None,
+        }
+    }
+
+    /// The text `rustc --explain` prints for this error's code
+    pub fn explanation(&self) -> Option<&'static str> {
+        self.code().and_then(explain)
+    }
+
+    /// The kind of the borrowcheck error or of the checker error matching a Polonius error.
+    /// Errors that aren't about the checked code (e.g. failing to load a workspace) have none.
+    pub fn kind(&self) -> Option<CheckerErrorKind> {
+        match self {
+            CheckError::Borrowcheck { error, .. } => Some(error.kind()),
+            CheckError::Polonius(PoloniusError::LoanInvalidated { .. }) => {
+                Some(CheckerErrorKind::Invalid)
+            }
+            CheckError::Polonius(PoloniusError::Moved { .. }) => Some(CheckerErrorKind::Moved),
+            CheckError::Polonius(_)
+            | CheckError::Workspace(_)
+            | CheckError::Panicked(_)
+            | CheckError::Unsupported { .. } => None,
+        }
+    }
+
+    /// Applies `map` to every source range contained in a borrowcheck error
+    pub fn map_ranges(self, map: impl Fn(TextRange) -> TextRange) -> Self {
+        match self {
+            CheckError::Borrowcheck { error, range } => CheckError::Borrowcheck {
+                range: map(range),
+                error: error.map_ranges(map),
+            },
+            CheckError::Unsupported { syntax, range } => CheckError::Unsupported {
+                syntax,
+                range: map(range),
+            },
+            error => error,
+        }
+    }
+
+    /// The source range the error has been reported at (like rustc would), if it is known
+    pub fn range(&self) -> Option<TextRange> {
+        match self {
+            CheckError::Borrowcheck {
+                error: CheckerError::Invalid(_, _, invalidation),
+                ..
+            } => Some(invalidation.primary_range()),
+            CheckError::Borrowcheck { range, .. } | CheckError::Unsupported { range, .. } => {
+                Some(*range)
+            }
+            CheckError::Polonius(PoloniusError::LoanInvalidated { range, .. })
+            | CheckError::Polonius(PoloniusError::Moved { range, .. }) => *range,
+            CheckError::Polonius(_) | CheckError::Workspace(_) | CheckError::Panicked(_) => None,
+        }
+    }
+
+    /// Renders the error with its rustc code and, if a borrow has been invalidated, the borrows that led to it
+    pub fn render(&self, source: &str) -> String {
+        let mut rendered = match self.code() {
+            Some(code) => format!("error[{}]: {}", code, self),
+            None => format!("error: {}", self),
+        };
+        if let Some(range) = self.range() {
+            rendered += &format!("\n  --> line {}", line_of(source, range));
+        }
+        if let CheckError::Borrowcheck {
+            error: CheckerError::Invalid(used, _, invalidation),
+            range,
+        } = self
+        {
+            rendered += "\n  = note: ";
+            rendered += &invalidation.describe(used, *range, |range| line_of(source, range));
+        }
+        rendered
+    }
+
+    fn at(range: TextRange) -> impl FnOnce(CheckerError) -> CheckError {
+        move |error| CheckError::Borrowcheck { error, range }
+    }
+
+    fn unsupported(node: &SyntaxNode) -> CheckError {
+        CheckError::Unsupported {
+            syntax: node.text().to_string(),
+            range: node.text_range(),
+        }
+    }
 }
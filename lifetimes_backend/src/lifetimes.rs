@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use syntax::TextRange;
+
+use crate::{checker::map_shadowed_lines, line_of, Loan, VarKind, VarSnapshot};
+
+/// What a lifetime belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LifetimeKind {
+    Local,
+    Param,
+    SharedLoan,
+    MutLoan,
+}
+
+/// The lines over which a local or a loan is live, from its creation to its last use.
+/// Lines are zero-based (like in the editor) and inclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lifetime {
+    /// The local's name or the borrow expression (e.g. `&mut x`)
+    pub name: String,
+    pub kind: LifetimeKind,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Where a loan has been invalidated (e.g. by a conflicting borrow), if it has been
+    pub invalidated_line: Option<usize>,
+    /// Lifetimes whose lines overlap never share a lane
+    pub lane: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Glyph {
+    Start,
+    Continue,
+    End,
+    /// The lifetime starts and ends on the same line
+    Single,
+}
+
+/// One bar segment of the gutter. `lifetime` is the index in `FunctionLifetimes::lifetimes`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GutterCell {
+    pub lane: usize,
+    pub glyph: Glyph,
+    pub lifetime: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GutterLine {
+    pub line: usize,
+    /// Sorted by lane
+    pub cells: Vec<GutterCell>,
+}
+
+/// The lifetimes of a function, ready to be rendered as stacked bars in the gutter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionLifetimes {
+    pub name: String,
+    /// The number of lanes needed to render all lifetimes
+    pub lanes: usize,
+    /// Sorted by start line, then by creation
+    pub lifetimes: Vec<Lifetime>,
+    /// Only contains lines that are covered by at least one lifetime
+    pub gutter: Vec<GutterLine>,
+}
+
+impl FunctionLifetimes {
+    pub(crate) fn new(name: String, vars: &[VarSnapshot], loans: &[Loan], source: &str) -> Self {
+        let line = |range: TextRange| line_of(source, range) - 1;
+        let end_line = |range: TextRange| line(TextRange::empty(range.end()));
+
+        // A local has to live as long as the loans of it are used
+        let mut loan_ends = HashMap::new();
+        for loan in loans {
+            let end = loan_ends.entry(loan.borrowed).or_insert(0);
+            *end = end_line(loan.live_range).max(*end);
+        }
+
+        let locals = vars.iter().filter_map(|var| {
+            let kind = match var.kind {
+                VarKind::Local => LifetimeKind::Local,
+                VarKind::Param => LifetimeKind::Param,
+                _ => return None,
+            };
+            let live_range = var.live_range?;
+            Some(Lifetime {
+                name: var.identifier.clone(),
+                kind,
+                start_line: line(live_range),
+                end_line: end_line(live_range).max(loan_ends.get(&var.id).copied().unwrap_or(0)),
+                invalidated_line: None,
+                lane: 0,
+            })
+        });
+        let loans = loans.iter().map(|loan| Lifetime {
+            name: loan.text.clone(),
+            kind: if loan.is_mut {
+                LifetimeKind::MutLoan
+            } else {
+                LifetimeKind::SharedLoan
+            },
+            start_line: line(loan.live_range),
+            end_line: end_line(loan.live_range),
+            invalidated_line: loan.invalidated_at.map(line),
+            lane: 0,
+        });
+
+        let mut lifetimes = locals.chain(loans).collect::<Vec<_>>();
+        // Stable, so lifetimes starting on the same line keep the order in which they were created
+        lifetimes.sort_by_key(|lifetime| lifetime.start_line);
+        let lanes = assign_lanes(&mut lifetimes);
+        let gutter = gutter(&lifetimes);
+
+        Self {
+            name,
+            lanes,
+            lifetimes,
+            gutter,
+        }
+    }
+
+    /// Applies `map` to every line, e.g. to move the lifetimes along with their function
+    pub fn map_lines(mut self, map: impl Fn(usize) -> usize) -> Self {
+        for lifetime in &mut self.lifetimes {
+            // The names of shadowed locals mention the (one-based) lines they are declared on
+            lifetime.name = map_shadowed_lines(&lifetime.name, |line| map(line - 1) + 1);
+            lifetime.start_line = map(lifetime.start_line);
+            lifetime.end_line = map(lifetime.end_line);
+            lifetime.invalidated_line = lifetime.invalidated_line.map(&map);
+        }
+        for gutter_line in &mut self.gutter {
+            gutter_line.line = map(gutter_line.line);
+        }
+        self
+    }
+}
+
+/// Puts every lifetime into the first lane that is free at its start line. Returns the number of lanes.
+fn assign_lanes(lifetimes: &mut [Lifetime]) -> usize {
+    let mut lane_ends: Vec<usize> = Vec::new();
+    for lifetime in lifetimes {
+        let lane = match lane_ends.iter().position(|end| *end < lifetime.start_line) {
+            Some(lane) => lane,
+            None => {
+                lane_ends.push(0);
+                lane_ends.len() - 1
+            }
+        };
+        lane_ends[lane] = lifetime.end_line;
+        lifetime.lane = lane;
+    }
+    lane_ends.len()
+}
+
+fn gutter(lifetimes: &[Lifetime]) -> Vec<GutterLine> {
+    let mut lines: Vec<GutterLine> = Vec::new();
+    let (first, last) = match (
+        lifetimes.iter().map(|lifetime| lifetime.start_line).min(),
+        lifetimes.iter().map(|lifetime| lifetime.end_line).max(),
+    ) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return lines,
+    };
+
+    for line in first..=last {
+        let mut cells = lifetimes
+            .iter()
+            .enumerate()
+            .filter(|(_, lifetime)| lifetime.start_line <= line && line <= lifetime.end_line)
+            .map(|(index, lifetime)| GutterCell {
+                lane: lifetime.lane,
+                glyph: if lifetime.start_line == lifetime.end_line {
+                    Glyph::Single
+                } else if line == lifetime.start_line {
+                    Glyph::Start
+                } else if line == lifetime.end_line {
+                    Glyph::End
+                } else {
+                    Glyph::Continue
+                },
+                lifetime: index,
+            })
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            continue;
+        }
+        cells.sort_by_key(|cell| cell.lane);
+        lines.push(GutterLine { line, cells });
+    }
+
+    lines
+}
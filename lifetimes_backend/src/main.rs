@@ -1,11 +1,410 @@
-use lifetimes_backend::check;
+//! The `lifetimes` command line interface.
+//!
+//! Exits with 0 if no errors were found, 1 if the checker reported errors and 2 if the input couldn't be checked
+//! (e.g. because it contains syntax the checker doesn't support yet).
+
+use std::{
+    fs,
+    io::{self, Read},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+};
+
+use clap::{ArgEnum, Args, Parser, Subcommand};
+use lifetimes_backend::{
+    check_with_options, check_workspace, Backend, Borrow, CheckError, CheckOptions,
+    CompilerMessage, Diagnostic, FileReport, FunctionTrace, SarifLog, Target, VarKind, VarSnapshot,
+    VarStatus, WorkspaceOptions,
+};
+use serde_json::json;
+
+const EXIT_ERRORS: i32 = 1;
+const EXIT_FAILURE: i32 = 2;
+
+#[derive(Parser)]
+#[clap(name = "lifetimes", version, about = "Borrow checks Rust code")]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checks a file, every `.rs` file in a directory, or a Cargo workspace
+    Check(CheckArgs),
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// A Rust file, a directory, a `Cargo.toml`, or `-` to read a file from stdin
+    input: PathBuf,
+
+    /// Only check the function with this path (e.g. `main` or `module::function`)
+    #[clap(long)]
+    function: Option<String>,
+
+    /// Only check this package of a Cargo workspace
+    #[clap(long)]
+    package: Option<String>,
+
+    #[clap(long, arg_enum, default_value = "native")]
+    backend: BackendArg,
+
+    #[clap(long, arg_enum, default_value = "human")]
+    format: Format,
+
+    /// Whether human-readable errors are coloured
+    #[clap(long, arg_enum, default_value = "auto")]
+    color: Color,
+
+    /// Print the borrow state after every statement
+    #[clap(long)]
+    trace: bool,
+
+    /// Print the borrow graph after the statements starting at this line as a Graphviz graph
+    #[clap(long, value_name = "LINE")]
+    dot_at: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum BackendArg {
+    Native,
+    Polonius,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Format {
+    Human,
+    Json,
+    Sarif,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Color {
+    /// Only if stderr is a terminal
+    Auto,
+    Always,
+    Never,
+}
 
 fn main() {
-    //tracing_subscriber::fmt().compact().init();
     env_logger::builder()
-        .filter_level(log::LevelFilter::Error)
-        .filter_module("lifetimes_backend", log::LevelFilter::Trace)
+        .filter_level(log::LevelFilter::Warn)
+        .parse_default_env()
         .init();
+    // Files the checker panics on are listed as unchecked after the reports, the messages would only get between them
+    panic::set_hook(Box::new(|info| log::debug!("{}", info)));
+
+    let exit_code = match Cli::parse().command {
+        Command::Check(args) => check(args),
+    };
+    process::exit(exit_code);
+}
+
+fn check(args: CheckArgs) -> i32 {
+    // The borrow graphs are taken from the trace
+    let trace = args.trace || args.dot_at.is_some();
+    if trace && args.format != Format::Human {
+        eprintln!("error: --trace and --dot-at are only supported with --format human");
+        return EXIT_FAILURE;
+    }
+    if trace && args.backend == BackendArg::Polonius {
+        eprintln!("error: --trace and --dot-at are only supported by the native backend");
+        return EXIT_FAILURE;
+    }
+
+    let options = CheckOptions {
+        backend: match args.backend {
+            BackendArg::Native => Backend::Native,
+            BackendArg::Polonius => Backend::Polonius,
+        },
+        function: args.function.clone(),
+        trace,
+    };
+
+    let (reports, unchecked) = match collect_reports(&args, &options) {
+        Ok(checked) => checked,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return EXIT_FAILURE;
+        }
+    };
+
+    let color = match args.color {
+        Color::Auto => atty::is(atty::Stream::Stderr),
+        Color::Always => true,
+        Color::Never => false,
+    };
+    let manifest = manifest_of(&args.input);
+    for report in &reports {
+        match args.format {
+            Format::Human => print_human(report, &args, color),
+            Format::Json => print_json(report, manifest.as_deref()),
+            // The SARIF log is printed as a whole once every file has been checked
+            Format::Sarif => {}
+        }
+    }
+    for path in &unchecked {
+        let message = format!(
+            "{} contains code the checker doesn't support yet",
+            path.display()
+        );
+        match args.format {
+            Format::Human => eprintln!("{}\n", Diagnostic::error(message).render(color)),
+            Format::Json => println!(
+                "{}",
+                json!(compiler_message(
+                    path,
+                    None,
+                    manifest.as_deref(),
+                    Diagnostic::error(message)
+                ))
+            ),
+            Format::Sarif => {}
+        }
+    }
+
+    let failed = reports
+        .iter()
+        .filter(|report| report.result.is_err())
+        .count();
+    match args.format {
+        Format::Human => eprintln!(
+            "checked {} files: {} with errors, {} unsupported",
+            reports.len() + unchecked.len(),
+            failed,
+            unchecked.len()
+        ),
+        Format::Json => println!(
+            "{}",
+            json!({
+                "reason": "build-finished",
+                "success": failed == 0 && unchecked.is_empty(),
+            })
+        ),
+        Format::Sarif => println!(
+            "{}",
+            serde_json::to_string_pretty(&SarifLog::new(&reports, &unchecked)).unwrap()
+        ),
+    }
+
+    if !unchecked.is_empty() {
+        EXIT_FAILURE
+    } else if failed > 0 {
+        EXIT_ERRORS
+    } else {
+        0
+    }
+}
+
+/// Checks the input and returns the reports of the checked files and the paths of the files whose check panicked
+fn collect_reports(
+    args: &CheckArgs,
+    options: &CheckOptions,
+) -> Result<(Vec<FileReport>, Vec<PathBuf>), String> {
+    let input = &args.input;
+    let mut reports = Vec::new();
+    let mut unchecked = Vec::new();
+
+    let mut check_code = |path: PathBuf, code: String| {
+        let source = Arc::new(code.clone());
+        match panic::catch_unwind(AssertUnwindSafe(|| check_with_options(code, options))) {
+            Ok((traces, result)) => reports.push(FileReport {
+                path,
+                krate: None,
+                source,
+                traces,
+                result,
+            }),
+            Err(_) => unchecked.push(path),
+        }
+    };
+
+    if input == Path::new("-") {
+        let mut code = String::new();
+        io::stdin()
+            .read_to_string(&mut code)
+            .map_err(|error| format!("failed to read stdin: {}", error))?;
+        check_code(PathBuf::from("<stdin>"), code);
+    } else if let Some(manifest) = manifest_of(input) {
+        let options = WorkspaceOptions {
+            package: args.package.clone(),
+            check: options.clone(),
+            ..WorkspaceOptions::default()
+        };
+        // The files are checked one by one, a panic only leaves its own file unchecked
+        for report in check_workspace(&manifest, &options).map_err(|error| error.to_string())? {
+            match report.result {
+                Err(CheckError::Panicked(_)) => unchecked.push(report.path),
+                _ => reports.push(report),
+            }
+        }
+    } else if input.is_dir() {
+        for path in rust_files(input).map_err(|error| error.to_string())? {
+            let code = fs::read_to_string(&path).map_err(|error| error.to_string())?;
+            check_code(path, code);
+        }
+    } else {
+        let code = fs::read_to_string(input)
+            .map_err(|error| format!("failed to read {}: {}", input.display(), error))?;
+        check_code(input.clone(), code);
+    }
+
+    Ok((reports, unchecked))
+}
+
+/// The manifest of a Cargo workspace, if `input` is one or a directory containing one
+fn manifest_of(input: &Path) -> Option<PathBuf> {
+    if input.file_name().map_or(false, |name| name == "Cargo.toml") {
+        Some(input.to_path_buf())
+    } else if input.join("Cargo.toml").is_file() {
+        Some(input.join("Cargo.toml"))
+    } else {
+        None
+    }
+}
+
+/// All `.rs` files below `dir`, sorted by path
+fn rust_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(rust_files(&path)?);
+        } else if path
+            .extension()
+            .map_or(false, |extension| extension == "rs")
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn print_human(report: &FileReport, args: &CheckArgs, color: bool) {
+    for trace in &report.traces {
+        if args.trace {
+            print_trace(trace, &report.source);
+        }
+        if let Some(line) = args.dot_at {
+            for snapshot in &trace.statements {
+                if line_of(&report.source, snapshot.range.start().into()) == line {
+                    print!("{}", snapshot.to_dot());
+                }
+            }
+        }
+    }
+    if let Err(error) = &report.result {
+        let diagnostic = error.diagnostic(&report.path.display().to_string(), &report.source);
+        eprintln!("{}\n", diagnostic.render(color));
+    }
+}
+
+/// Prints errors like `cargo build --message-format=json` does
+fn print_json(report: &FileReport, manifest: Option<&Path>) {
+    if let Err(error) = &report.result {
+        let diagnostic = error.diagnostic(&report.path.display().to_string(), &report.source);
+        let message = compiler_message(&report.path, report.krate.as_deref(), manifest, diagnostic);
+        println!("{}", json!(message));
+    }
+}
+
+/// Wraps `diagnostic` like cargo does. A file that isn't part of a workspace is treated as the root of a package
+/// named after it.
+fn compiler_message(
+    path: &Path,
+    krate: Option<&str>,
+    manifest: Option<&Path>,
+    diagnostic: Diagnostic,
+) -> CompilerMessage {
+    let name = krate.map_or_else(
+        || {
+            path.file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        },
+        str::to_string,
+    );
+    let package_dir = manifest
+        .and_then(Path::parent)
+        .or_else(|| path.parent())
+        .unwrap_or_else(|| Path::new("."));
+    CompilerMessage::new(
+        format!("{} 0.0.0 (path+file://{})", name, package_dir.display()),
+        manifest.unwrap_or(path).display().to_string(),
+        Target::new(name, path.display().to_string()),
+        diagnostic,
+    )
+}
+
+fn print_trace(trace: &FunctionTrace, source: &str) {
+    println!("fn {}", trace.name);
+    for snapshot in &trace.statements {
+        let statement = &source[snapshot.range];
+        println!(
+            "  line {}: {}",
+            line_of(source, snapshot.range.start().into()),
+            statement.lines().next().unwrap_or_default().trim()
+        );
+        for var in &snapshot.vars {
+            if matches!(var.kind, VarKind::Local | VarKind::Param) {
+                println!("    {}", describe_var(var, &snapshot.vars));
+            }
+        }
+    }
+    println!();
+}
+
+fn describe_var(var: &VarSnapshot, vars: &[VarSnapshot]) -> String {
+    let name = |id| {
+        vars.iter()
+            .find(|var| var.id == id)
+            .map_or("?", |var| var.identifier.as_str())
+            .trim_start_matches("<tmp> ")
+    };
+
+    let mut description = format!(
+        "{}: {}",
+        var.identifier,
+        match &var.status {
+            VarStatus::Unitialized => "uninitialized".to_string(),
+            VarStatus::Initialized => "initialized".to_string(),
+            VarStatus::Moved => "moved".to_string(),
+            VarStatus::MutBorrowed(borrower) =>
+                format!("borrowed mutably by `{}`", name(*borrower)),
+            VarStatus::Reserved(borrower, readers) =>
+                std::iter::once(format!("reserved by `{}`", name(*borrower)))
+                    .chain(
+                        readers
+                            .iter()
+                            .map(|reader| format!("borrowed by `{}`", name(*reader)))
+                    )
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            VarStatus::Borrowed(borrowers) => format!(
+                "borrowed by {}",
+                borrowers
+                    .iter()
+                    .map(|borrower| format!("`{}`", name(*borrower)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    );
+    for borrow in &var.borrows {
+        description += &match borrow {
+            Borrow::Mutable(target) => format!(", borrows `{}` mutably", name(*target)),
+            Borrow::Immutable(target) => format!(", borrows `{}`", name(*target)),
+        };
+    }
+    if let Some(invalidation) = &var.invalidation {
+        description += &format!(", invalid ({})", invalidation);
+    }
+    description
+}
 
-    check(std::fs::read_to_string("../scratch/src/main.rs").unwrap()).unwrap();
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
 }
@@ -0,0 +1,144 @@
+use std::fmt::Write;
+
+use syntax::TextRange;
+
+use crate::{line_of, OriginId, OriginInfo, OriginKind, VarKind, VarSnapshot};
+
+/// The origins of a function: its scopes nested in each other and the lifetime parameters of its signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionOrigins {
+    pub name: String,
+    /// In the order of creation, so parents come before their children
+    pub origins: Vec<OriginInfo>,
+    /// Every var of the function, see `OriginInfo::vars`
+    pub vars: Vec<VarSnapshot>,
+}
+
+impl FunctionOrigins {
+    pub fn origin(&self, id: OriginId) -> Option<&OriginInfo> {
+        self.origins.iter().find(|origin| origin.id == id)
+    }
+
+    /// The origin and its parents, innermost first
+    pub fn ancestors(&self, id: OriginId) -> Vec<&OriginInfo> {
+        let mut ancestors = Vec::new();
+        let mut origin = self.origin(id);
+        while let Some(current) = origin {
+            ancestors.push(current);
+            origin = current.parent.and_then(|parent| self.origin(parent));
+        }
+        ancestors
+    }
+
+    /// The origins as an indented tree, each with its source lines and vars, e.g.
+    ///
+    /// ```text
+    /// #0 'static
+    ///   #1 parameters (lines 1-4): a
+    ///     #3 block (lines 1-4): x
+    ///   #2 'a (line 1), outlives #1
+    /// ```
+    pub fn to_text(&self, source: &str) -> String {
+        let mut text = String::new();
+        for origin in self.origins.iter().filter(|origin| origin.parent.is_none()) {
+            self.write_tree(&mut text, origin, 0, source);
+        }
+        text
+    }
+
+    fn write_tree(&self, text: &mut String, origin: &OriginInfo, depth: usize, source: &str) {
+        let mut line = format!("{}{}", "  ".repeat(depth), self.describe(origin, source));
+        let vars = self.var_names(origin);
+        if !vars.is_empty() {
+            line += &format!(": {}", vars.join(", "));
+        }
+        if !origin.outlives.is_empty() {
+            let outlived = origin
+                .outlives
+                .iter()
+                .map(|origin| origin.to_string())
+                .collect::<Vec<_>>();
+            line += &format!(", outlives {}", outlived.join(", "));
+        }
+        writeln!(text, "{}", line).unwrap();
+
+        for child in self
+            .origins
+            .iter()
+            .filter(|child| child.parent == Some(origin.id))
+        {
+            self.write_tree(text, child, depth + 1, source);
+        }
+    }
+
+    /// The origins as a Graphviz graph: every origin points to the origins nested in it, declared outlives
+    /// relations are dashed
+    pub fn to_dot(&self, source: &str) -> String {
+        let mut dot =
+            String::from("digraph origins {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for origin in &self.origins {
+            let mut label = self.describe(origin, source);
+            let vars = self.var_names(origin);
+            if !vars.is_empty() {
+                label += &format!("\n{}", vars.join(", "));
+            }
+            writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\"];",
+                origin.id,
+                label.replace('"', "\\\"").replace('\n', "\\n")
+            )
+            .unwrap();
+        }
+        for origin in &self.origins {
+            if let Some(parent) = origin.parent {
+                writeln!(dot, "    \"{}\" -> \"{}\";", parent, origin.id).unwrap();
+            }
+            for shorter in &origin.outlives {
+                writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\" [style=dashed, label=\"outlives\"];",
+                    origin.id, shorter
+                )
+                .unwrap();
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+
+    /// e.g. `#2 block (lines 3-5)`
+    fn describe(&self, origin: &OriginInfo, source: &str) -> String {
+        let kind = match &origin.kind {
+            OriginKind::Static => "'static".to_string(),
+            OriginKind::Signature(name) => name.clone(),
+            OriginKind::Parameters => "parameters".to_string(),
+            OriginKind::Block => "block".to_string(),
+        };
+        match origin.range {
+            Some(range) => format!("{} {} ({})", origin.id, kind, lines(range, source)),
+            None => format!("{} {}", origin.id, kind),
+        }
+    }
+
+    /// The locals, parameters and temporaries living in the origin
+    fn var_names(&self, origin: &OriginInfo) -> Vec<String> {
+        origin
+            .vars
+            .iter()
+            .filter_map(|id| self.vars.iter().find(|var| var.id == *id))
+            .filter(|var| !matches!(var.kind, VarKind::Literal))
+            .map(|var| var.identifier.clone())
+            .collect()
+    }
+}
+
+fn lines(range: TextRange, source: &str) -> String {
+    let start = line_of(source, range);
+    let end = line_of(source, TextRange::empty(range.end()));
+    if start == end {
+        format!("line {}", start)
+    } else {
+        format!("lines {}-{}", start, end)
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path as FsPath,
+};
+
+use polonius_engine::{AllFacts, Atom, FactTypes};
+
+use super::PoloniusError;
+
+macro_rules! atoms {
+    ($($name:ident => $interner:ident),* $(,)?) => {
+        $(
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name(usize);
+
+            impl From<usize> for $name {
+                fn from(index: usize) -> Self {
+                    Self(index)
+                }
+            }
+
+            impl From<$name> for usize {
+                fn from(atom: $name) -> usize {
+                    atom.0
+                }
+            }
+
+            impl Atom for $name {
+                fn index(self) -> usize {
+                    self.0
+                }
+            }
+
+            impl FactCell for $name {
+                fn render(self, interners: &Interners) -> &str {
+                    interners.$interner.name(self.0)
+                }
+
+                fn parse(text: &str, interners: &mut Interners) -> Self {
+                    Self(interners.$interner.intern(text))
+                }
+            }
+
+            impl FactRow for $name {
+                const ARITY: usize = 1;
+
+                fn cells<'i>(&self, interners: &'i Interners) -> Vec<&'i str> {
+                    vec![self.render(interners)]
+                }
+
+                fn from_cells(cells: &[&str], interners: &mut Interners) -> Self {
+                    Self::parse(cells[0], interners)
+                }
+            }
+        )*
+    };
+}
+
+atoms! {
+    Origin => origins,
+    Loan => loans,
+    Point => points,
+    Variable => variables,
+    Path => paths,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LifetimesFacts;
+
+impl FactTypes for LifetimesFacts {
+    type Origin = Origin;
+    type Loan = Loan;
+    type Point = Point;
+    type Variable = Variable;
+    type Path = Path;
+}
+
+/// Maps atoms to the names used in `.facts` files (e.g. `'?3`, `bw0` or `Mid(bb0[2])`) and back
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(index) = self.indices.get(name) {
+            *index
+        } else {
+            let index = self.names.len();
+            self.names.push(name.to_string());
+            self.indices.insert(name.to_string(), index);
+            index
+        }
+    }
+
+    pub fn name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Interners {
+    pub origins: Interner,
+    pub loans: Interner,
+    pub points: Interner,
+    pub variables: Interner,
+    pub paths: Interner,
+}
+
+/// The facts of a single function together with the names of their atoms
+#[derive(Debug, Clone)]
+pub struct FunctionFacts {
+    pub name: String,
+    pub facts: AllFacts<LifetimesFacts>,
+    pub interners: Interners,
+}
+
+macro_rules! relations {
+    ($($relation:ident),* $(,)?) => {
+        impl FunctionFacts {
+            pub fn new(name: String) -> Self {
+                Self {
+                    name,
+                    facts: AllFacts::default(),
+                    interners: Interners::default(),
+                }
+            }
+
+            /// Writes the facts to `<dir>/<function name>/<relation>.facts`, which is the layout produced by `rustc -Znll-facts`
+            pub fn write_to_dir(&self, dir: &FsPath) -> Result<(), PoloniusError> {
+                let function_dir = dir.join(&self.name);
+                fs::create_dir_all(&function_dir)?;
+                $(
+                    write_relation(
+                        &function_dir.join(concat!(stringify!($relation), ".facts")),
+                        &self.facts.$relation,
+                        &self.interners,
+                    )?;
+                )*
+                Ok(())
+            }
+
+            /// Reads the facts of one function from a directory in the layout produced by `rustc -Znll-facts`.
+            /// Missing relation files are treated as empty.
+            pub fn read_from_dir(function_dir: &FsPath) -> Result<Self, PoloniusError> {
+                let name = function_dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut function_facts = Self::new(name);
+                $(
+                    read_relation(
+                        &function_dir.join(concat!(stringify!($relation), ".facts")),
+                        &mut function_facts.facts.$relation,
+                        &mut function_facts.interners,
+                    )?;
+                )*
+                Ok(function_facts)
+            }
+        }
+    };
+}
+
+relations! {
+    loan_issued_at,
+    universal_region,
+    cfg_edge,
+    loan_killed_at,
+    subset_base,
+    loan_invalidated_at,
+    var_used_at,
+    var_defined_at,
+    var_dropped_at,
+    use_of_var_derefs_origin,
+    drop_of_var_derefs_origin,
+    child_path,
+    path_is_var,
+    path_assigned_at_base,
+    path_moved_at_base,
+    path_accessed_at_base,
+    known_placeholder_subset,
+    placeholder,
+}
+
+/// Reads every function directory (e.g. `nll-facts/main`) contained in `dir`
+pub fn read_facts_dir(dir: &FsPath) -> Result<Vec<FunctionFacts>, PoloniusError> {
+    let mut function_dirs = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    function_dirs.retain(|path| path.is_dir());
+    function_dirs.sort();
+
+    function_dirs
+        .iter()
+        .map(|function_dir| FunctionFacts::read_from_dir(function_dir))
+        .collect()
+}
+
+trait FactCell: Sized {
+    fn render(self, interners: &Interners) -> &str;
+    fn parse(text: &str, interners: &mut Interners) -> Self;
+}
+
+trait FactRow: Sized {
+    const ARITY: usize;
+
+    fn cells<'i>(&self, interners: &'i Interners) -> Vec<&'i str>;
+    fn from_cells(cells: &[&str], interners: &mut Interners) -> Self;
+}
+
+impl<A: FactCell + Copy, B: FactCell + Copy> FactRow for (A, B) {
+    const ARITY: usize = 2;
+
+    fn cells<'i>(&self, interners: &'i Interners) -> Vec<&'i str> {
+        vec![self.0.render(interners), self.1.render(interners)]
+    }
+
+    fn from_cells(cells: &[&str], interners: &mut Interners) -> Self {
+        (A::parse(cells[0], interners), B::parse(cells[1], interners))
+    }
+}
+
+impl<A: FactCell + Copy, B: FactCell + Copy, C: FactCell + Copy> FactRow for (A, B, C) {
+    const ARITY: usize = 3;
+
+    fn cells<'i>(&self, interners: &'i Interners) -> Vec<&'i str> {
+        vec![
+            self.0.render(interners),
+            self.1.render(interners),
+            self.2.render(interners),
+        ]
+    }
+
+    fn from_cells(cells: &[&str], interners: &mut Interners) -> Self {
+        (
+            A::parse(cells[0], interners),
+            B::parse(cells[1], interners),
+            C::parse(cells[2], interners),
+        )
+    }
+}
+
+fn write_relation<R: FactRow>(
+    file: &FsPath,
+    rows: &[R],
+    interners: &Interners,
+) -> Result<(), PoloniusError> {
+    let mut writer = BufWriter::new(File::create(file)?);
+    for row in rows {
+        let cells = row
+            .cells(interners)
+            .into_iter()
+            .map(|cell| format!("{:?}", cell))
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", cells.join("\t"))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_relation<R: FactRow>(
+    file: &FsPath,
+    rows: &mut Vec<R>,
+    interners: &mut Interners,
+) -> Result<(), PoloniusError> {
+    let reader = match File::open(file) {
+        Ok(reader) => BufReader::new(reader),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let cells = line.split('\t').map(unquote).collect::<Vec<_>>();
+        if cells.len() != R::ARITY {
+            return Err(PoloniusError::MalformedFacts {
+                file: file.to_path_buf(),
+                line: line_index + 1,
+            });
+        }
+        let cells = cells.iter().map(String::as_str).collect::<Vec<_>>();
+        rows.push(R::from_cells(&cells, interners));
+    }
+
+    Ok(())
+}
+
+/// Reverses the `Debug` formatting of strings that rustc uses for every cell
+fn unquote(cell: &str) -> String {
+    let cell = cell.trim();
+    let cell = cell
+        .strip_prefix('"')
+        .and_then(|cell| cell.strip_suffix('"'))
+        .unwrap_or(cell);
+
+    let mut unquoted = String::with_capacity(cell.len());
+    let mut chars = cell.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                unquoted.push(escaped);
+            }
+        } else {
+            unquoted.push(c);
+        }
+    }
+    unquoted
+}
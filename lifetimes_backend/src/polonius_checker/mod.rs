@@ -0,0 +1,569 @@
+mod facts;
+
+use std::{collections::HashMap, path::PathBuf};
+
+use hir::{db::HirDatabase, Semantics};
+use log::{debug, trace};
+use polonius_engine::{Algorithm, Output};
+use syntax::{
+    ast::{self, AstNode, HasName},
+    TextRange,
+};
+
+pub use self::facts::{read_facts_dir, FunctionFacts};
+use self::facts::{Loan, Origin, Path, Point, Variable};
+
+/// Generates Polonius input facts for a single function.
+///
+/// Every evaluation step gets a `Start` and a `Mid` point (like rustc's location table).
+/// Branches of `if` expressions are placed into their own basic blocks.
+pub struct FactGenerator<'a, 'db, DB: HirDatabase> {
+    sema: &'a Semantics<'db, DB>,
+    function: FunctionFacts,
+    locals: HashMap<hir::Local, Local>,
+    loans: Vec<LoanData>,
+    point_ranges: HashMap<Point, TextRange>,
+    predecessors: Vec<Point>,
+    current_block: usize,
+    next_statement: usize,
+    block_count: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    variable: Variable,
+    path: Path,
+    origin: Origin,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LoanData {
+    path: Path,
+    is_mut: bool,
+}
+
+/// The origins carried by the value of an expression
+struct Operand {
+    origins: Vec<Origin>,
+}
+
+/// A place is a local with an arbitrary number of derefs applied to it
+#[derive(Debug, Clone, Copy)]
+struct Place {
+    local: Local,
+    path: Path,
+}
+
+impl<'a, 'db, DB: HirDatabase> FactGenerator<'a, 'db, DB> {
+    pub fn new(sema: &'a Semantics<'db, DB>, name: String) -> Self {
+        Self {
+            sema,
+            function: FunctionFacts::new(name),
+            locals: HashMap::new(),
+            loans: Vec::new(),
+            point_ranges: HashMap::new(),
+            predecessors: Vec::new(),
+            current_block: 0,
+            next_statement: 0,
+            block_count: 1,
+        }
+    }
+
+    pub fn generate(
+        mut self,
+        function: &ast::Fn,
+    ) -> Result<(FunctionFacts, HashMap<Point, TextRange>), PoloniusError> {
+        if let Some(params) = function.param_list() {
+            for param in params.params() {
+                if let Some(ast::Pat::IdentPat(ident)) = param.pat() {
+                    let local = self.create_local(&ident)?;
+                    let point = self.step(param.syntax().text_range());
+                    self.define(local, point);
+                }
+            }
+        }
+
+        if let Some(body) = function.body() {
+            self.process_block(&body)?;
+        }
+
+        Ok((self.function, self.point_ranges))
+    }
+
+    fn process_block(&mut self, block: &ast::BlockExpr) -> Result<Operand, PoloniusError> {
+        let stmt_list = block.stmt_list().ok_or(PoloniusError::Unsupported(
+            block.syntax().text().to_string(),
+        ))?;
+        for stmt in stmt_list.statements() {
+            trace!("Generating facts for '{}'", stmt.syntax().text());
+            self.process_statement(&stmt)?;
+        }
+
+        if let Some(expr) = stmt_list.tail_expr() {
+            self.process_expr(&expr)
+        } else {
+            Ok(Operand {
+                origins: Vec::new(),
+            })
+        }
+    }
+
+    fn process_statement(&mut self, stmt: &ast::Stmt) -> Result<(), PoloniusError> {
+        match stmt {
+            ast::Stmt::ExprStmt(expr) => {
+                if let Some(expr) = expr.expr() {
+                    self.process_expr(&expr)?;
+                }
+            }
+            ast::Stmt::LetStmt(let_stmt) => {
+                let ident = match let_stmt.pat() {
+                    Some(ast::Pat::IdentPat(ident)) => ident,
+                    _ => {
+                        return Err(PoloniusError::Unsupported(
+                            let_stmt.syntax().text().to_string(),
+                        ))
+                    }
+                };
+                let local = self.create_local(&ident)?;
+
+                if let Some(init) = let_stmt.initializer() {
+                    let value = self.process_expr(&init)?;
+                    let point = self.step(let_stmt.syntax().text_range());
+                    self.assign(
+                        Place {
+                            local,
+                            path: local.path,
+                        },
+                        value,
+                        point,
+                    );
+                }
+            }
+            ast::Stmt::Item(_) => {}
+        }
+        Ok(())
+    }
+
+    fn process_expr(&mut self, expr: &ast::Expr) -> Result<Operand, PoloniusError> {
+        match expr {
+            ast::Expr::Literal(_) => Ok(Operand {
+                origins: Vec::new(),
+            }),
+            ast::Expr::PathExpr(_) | ast::Expr::PrefixExpr(_) => {
+                let place = self.resolve_place(expr)?;
+                let point = self.step(expr.syntax().text_range());
+                self.read(place, point);
+
+                let is_copy = self
+                    .sema
+                    .type_of_expr(expr)
+                    .map(|ty| ty.original().is_copy(self.sema.db))
+                    .unwrap_or(false);
+                if !is_copy {
+                    self.function
+                        .facts
+                        .path_moved_at_base
+                        .push((place.path, point));
+                }
+
+                Ok(Operand {
+                    origins: vec![place.local.origin],
+                })
+            }
+            ast::Expr::RefExpr(ref_expr) => {
+                let is_mut = ref_expr.mut_token().is_some();
+                let target = ref_expr
+                    .expr()
+                    .ok_or_else(|| PoloniusError::Unsupported(expr.syntax().text().to_string()))?;
+                let place = self.resolve_place(&target)?;
+
+                let point = self.step(expr.syntax().text_range());
+                self.access(place.path, is_mut, point);
+                let origin = self.new_origin();
+                let loan = self.new_loan(place.path, is_mut);
+                self.function
+                    .facts
+                    .loan_issued_at
+                    .push((origin, loan, point));
+                self.function
+                    .facts
+                    .var_used_at
+                    .push((place.local.variable, point));
+
+                // Reborrows must not outlive the reference they were created from
+                if place.path != place.local.path {
+                    self.function
+                        .facts
+                        .subset_base
+                        .push((place.local.origin, origin, point));
+                }
+
+                Ok(Operand {
+                    origins: vec![origin],
+                })
+            }
+            ast::Expr::BinExpr(bin_expr) => match bin_expr.op_kind() {
+                Some(ast::BinaryOp::Assignment { .. }) => {
+                    let rhs = bin_expr.rhs().ok_or_else(|| {
+                        PoloniusError::Unsupported(expr.syntax().text().to_string())
+                    })?;
+                    let lhs = bin_expr.lhs().ok_or_else(|| {
+                        PoloniusError::Unsupported(expr.syntax().text().to_string())
+                    })?;
+                    let value = self.process_expr(&rhs)?;
+                    let place = self.resolve_place(&lhs)?;
+                    let point = self.step(expr.syntax().text_range());
+                    self.assign(place, value, point);
+                    Ok(Operand {
+                        origins: Vec::new(),
+                    })
+                }
+                _ => Err(PoloniusError::Unsupported(expr.syntax().text().to_string())),
+            },
+            ast::Expr::IfExpr(if_expr) => {
+                let cond = if_expr
+                    .condition()
+                    .and_then(|cond| cond.expr())
+                    .ok_or_else(|| PoloniusError::Unsupported(expr.syntax().text().to_string()))?;
+                self.process_expr(&cond)?;
+                let branch_point = self.step(cond.syntax().text_range());
+
+                let mut branch_ends = Vec::new();
+                let mut origins = Vec::new();
+                let mut has_else = false;
+
+                let mut branches = vec![if_expr.then_branch()];
+                let mut else_branch = if_expr.else_branch();
+                while let Some(branch) = else_branch {
+                    match branch {
+                        ast::ElseBranch::Block(block) => {
+                            branches.push(Some(block));
+                            has_else = true;
+                            break;
+                        }
+                        ast::ElseBranch::IfExpr(expr) => {
+                            branches.push(expr.then_branch());
+                            else_branch = expr.else_branch();
+                        }
+                    }
+                }
+
+                for branch in branches.into_iter().flatten() {
+                    self.enter_block(branch_point);
+                    origins.extend(self.process_block(&branch)?.origins);
+                    branch_ends.append(&mut self.predecessors);
+                }
+                if !has_else {
+                    branch_ends.push(branch_point);
+                }
+
+                self.join_blocks(branch_ends);
+
+                let point = self.step(expr.syntax().text_range());
+                let result_origin = self.new_origin();
+                for origin in origins {
+                    self.function
+                        .facts
+                        .subset_base
+                        .push((origin, result_origin, point));
+                }
+
+                Ok(Operand {
+                    origins: vec![result_origin],
+                })
+            }
+            ast::Expr::BlockExpr(block) => self.process_block(block),
+            ast::Expr::ParenExpr(paren) => match paren.expr() {
+                Some(expr) => self.process_expr(&expr),
+                None => Err(PoloniusError::Unsupported(expr.syntax().text().to_string())),
+            },
+            _ => Err(PoloniusError::Unsupported(expr.syntax().text().to_string())),
+        }
+    }
+
+    fn resolve_place(&mut self, expr: &ast::Expr) -> Result<Place, PoloniusError> {
+        match expr {
+            ast::Expr::PathExpr(path) => {
+                let local = path
+                    .path()
+                    .and_then(|path| match self.sema.resolve_path(&path) {
+                        Some(hir::PathResolution::Local(local)) => Some(local),
+                        _ => None,
+                    })
+                    .and_then(|local| self.locals.get(&local).copied())
+                    .ok_or_else(|| PoloniusError::Unsupported(expr.syntax().text().to_string()))?;
+                Ok(Place {
+                    local,
+                    path: local.path,
+                })
+            }
+            ast::Expr::PrefixExpr(prefix_expr)
+                if prefix_expr.op_kind() == Some(ast::UnaryOp::Deref) =>
+            {
+                let inner = prefix_expr
+                    .expr()
+                    .ok_or_else(|| PoloniusError::Unsupported(expr.syntax().text().to_string()))?;
+                let place = self.resolve_place(&inner)?;
+                // The path of the local is unique, so is the path of its deref
+                let name = format!("*{}", self.function.interners.paths.name(place.path.into()));
+                let path = Path::from(self.function.interners.paths.intern(&name));
+                if !self.function.facts.child_path.contains(&(path, place.path)) {
+                    self.function.facts.child_path.push((path, place.path));
+                }
+                Ok(Place {
+                    local: place.local,
+                    path,
+                })
+            }
+            ast::Expr::ParenExpr(paren) => match paren.expr() {
+                Some(inner) => self.resolve_place(&inner),
+                None => Err(PoloniusError::Unsupported(expr.syntax().text().to_string())),
+            },
+            _ => Err(PoloniusError::Unsupported(expr.syntax().text().to_string())),
+        }
+    }
+
+    fn create_local(&mut self, ident: &ast::IdentPat) -> Result<Local, PoloniusError> {
+        let hir_local = self
+            .sema
+            .to_def(ident)
+            .ok_or_else(|| PoloniusError::Unsupported(ident.syntax().text().to_string()))?;
+        let name = ident
+            .name()
+            .map(|name| name.text().to_string())
+            .unwrap_or_default();
+
+        let interners = &mut self.function.interners;
+        let variable = Variable::from(
+            interners
+                .variables
+                .intern(&format!("_{}", interners.variables.len() + 1)),
+        );
+        // Shadowed locals share their name, their paths are told apart by their variable
+        let path = Path::from(interners.paths.intern(&format!("{}@{:?}", name, variable)));
+        let origin = self.new_origin();
+
+        let local = Local {
+            variable,
+            path,
+            origin,
+        };
+        self.function.facts.path_is_var.push((path, variable));
+        self.function
+            .facts
+            .use_of_var_derefs_origin
+            .push((variable, origin));
+        self.locals.insert(hir_local, local);
+        debug!("Created local '{}' {:?}", name, local);
+        Ok(local)
+    }
+
+    fn read(&mut self, place: Place, point: Point) {
+        self.function
+            .facts
+            .var_used_at
+            .push((place.local.variable, point));
+        self.function
+            .facts
+            .path_accessed_at_base
+            .push((place.path, point));
+        self.access(place.path, false, point);
+    }
+
+    fn assign(&mut self, place: Place, value: Operand, point: Point) {
+        if place.path == place.local.path {
+            self.function
+                .facts
+                .var_defined_at
+                .push((place.local.variable, point));
+
+            // Overwriting a reference ends all loans that were accessed through it
+            let killed = self.loans_through(place.path);
+            for loan in killed {
+                self.function.facts.loan_killed_at.push((loan, point));
+            }
+        } else {
+            self.function
+                .facts
+                .var_used_at
+                .push((place.local.variable, point));
+        }
+
+        self.function
+            .facts
+            .path_assigned_at_base
+            .push((place.path, point));
+        self.access(place.path, true, point);
+
+        for origin in value.origins {
+            self.function
+                .facts
+                .subset_base
+                .push((origin, place.local.origin, point));
+        }
+    }
+
+    fn define(&mut self, local: Local, point: Point) {
+        self.function
+            .facts
+            .var_defined_at
+            .push((local.variable, point));
+        self.function
+            .facts
+            .path_assigned_at_base
+            .push((local.path, point));
+    }
+
+    /// Records all loans that conflict with an access to `path`.
+    /// Writes conflict with every loan of an overlapping path, reads only with mutable ones.
+    fn access(&mut self, path: Path, is_write: bool, point: Point) {
+        for (index, loan) in self.loans.iter().enumerate() {
+            if (is_write || loan.is_mut) && self.paths_overlap(path, loan.path) {
+                self.function
+                    .facts
+                    .loan_invalidated_at
+                    .push((point, Loan::from(index)));
+            }
+        }
+    }
+
+    fn loans_through(&self, path: Path) -> Vec<Loan> {
+        self.loans
+            .iter()
+            .enumerate()
+            .filter(|(_, loan)| loan.path != path && self.is_prefix(path, loan.path))
+            .map(|(index, _)| Loan::from(index))
+            .collect()
+    }
+
+    fn paths_overlap(&self, a: Path, b: Path) -> bool {
+        self.is_prefix(a, b) || self.is_prefix(b, a)
+    }
+
+    fn is_prefix(&self, prefix: Path, path: Path) -> bool {
+        let mut path = Some(path);
+        while let Some(p) = path {
+            if p == prefix {
+                return true;
+            }
+            path = self
+                .function
+                .facts
+                .child_path
+                .iter()
+                .find(|(child, _)| *child == p)
+                .map(|(_, parent)| *parent);
+        }
+        false
+    }
+
+    fn new_origin(&mut self) -> Origin {
+        let interner = &mut self.function.interners.origins;
+        Origin::from(interner.intern(&format!("'?{}", interner.len())))
+    }
+
+    fn new_loan(&mut self, path: Path, is_mut: bool) -> Loan {
+        let interner = &mut self.function.interners.loans;
+        let loan = Loan::from(interner.intern(&format!("bw{}", interner.len())));
+        self.loans.push(LoanData { path, is_mut });
+        loan
+    }
+
+    /// Creates the next point of the current block and connects it to its predecessor
+    fn step(&mut self, range: TextRange) -> Point {
+        let interner = &mut self.function.interners.points;
+        let start = Point::from(interner.intern(&format!(
+            "Start(bb{}[{}])",
+            self.current_block, self.next_statement
+        )));
+        let mid = Point::from(interner.intern(&format!(
+            "Mid(bb{}[{}])",
+            self.current_block, self.next_statement
+        )));
+        self.next_statement += 1;
+
+        for predecessor in std::mem::take(&mut self.predecessors) {
+            self.function.facts.cfg_edge.push((predecessor, start));
+        }
+        self.function.facts.cfg_edge.push((start, mid));
+        self.point_ranges.insert(start, range);
+        self.point_ranges.insert(mid, range);
+        self.predecessors = vec![mid];
+        mid
+    }
+
+    fn enter_block(&mut self, predecessor: Point) {
+        self.current_block = self.block_count;
+        self.block_count += 1;
+        self.next_statement = 0;
+        self.predecessors = vec![predecessor];
+    }
+
+    /// The first step after this call will be connected to all `predecessors`
+    fn join_blocks(&mut self, predecessors: Vec<Point>) {
+        self.current_block = self.block_count;
+        self.block_count += 1;
+        self.next_statement = 0;
+        self.predecessors = predecessors;
+    }
+}
+
+/// Runs Polonius on the given facts and reports the first error
+pub fn check_facts(
+    function: &FunctionFacts,
+    point_ranges: &HashMap<Point, TextRange>,
+) -> Result<(), PoloniusError> {
+    let output = Output::compute(&function.facts, Algorithm::Naive, false);
+
+    let mut errors = output.errors.into_iter().collect::<Vec<_>>();
+    errors.sort();
+    if let Some((point, loans)) = errors.into_iter().next() {
+        return Err(PoloniusError::LoanInvalidated {
+            function: function.name.clone(),
+            loan: function.interners.loans.name(loans[0].into()).to_string(),
+            point: function.interners.points.name(point.into()).to_string(),
+            range: point_ranges.get(&point).copied(),
+        });
+    }
+
+    let mut move_errors = output.move_errors.into_iter().collect::<Vec<_>>();
+    move_errors.sort();
+    if let Some((point, paths)) = move_errors.into_iter().next() {
+        return Err(PoloniusError::Moved {
+            function: function.name.clone(),
+            path: function.interners.paths.name(paths[0].into()).to_string(),
+            point: function.interners.points.name(point.into()).to_string(),
+            range: point_ranges.get(&point).copied(),
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PoloniusError {
+    #[error("In '{function}': loan {loan} is invalidated at {point} while it is still live")]
+    LoanInvalidated {
+        function: String,
+        loan: String,
+        point: String,
+        range: Option<TextRange>,
+    },
+
+    #[error("In '{function}': path '{path}' is accessed at {point} after it has been moved")]
+    Moved {
+        function: String,
+        path: String,
+        point: String,
+        range: Option<TextRange>,
+    },
+
+    #[error("Cannot generate facts for '{0}'")]
+    Unsupported(String),
+
+    #[error("Malformed facts in {file:?}, line {line}")]
+    MalformedFacts { file: PathBuf, line: usize },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,232 @@
+//! Renders diagnostics like rustc prints them in the terminal: the annotated source lines with the primary span
+//! underlined by `^` and the other spans by `-`, each with its label.
+
+use std::collections::BTreeMap;
+
+use crate::{Diagnostic, DiagnosticSpan, Level};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// An underlined part of a source line
+struct Annotation<'d> {
+    /// Zero-based character columns, `end` is exclusive and greater than `start`
+    start: usize,
+    end: usize,
+    is_primary: bool,
+    /// Multi-line spans are labelled on their last line only
+    label: Option<&'d str>,
+}
+
+/// Adds ANSI colours if enabled
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color && !text.is_empty() {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn level(&self, level: Level) -> String {
+        let (color, name) = match level {
+            Level::Error => (RED, "error"),
+            Level::Warning => (YELLOW, "warning"),
+            Level::Note => (GREEN, "note"),
+            Level::Help => (CYAN, "help"),
+        };
+        self.paint(color, name)
+    }
+
+    fn marker(&self, is_primary: bool, text: &str) -> String {
+        self.paint(if is_primary { RED } else { BLUE }, text)
+    }
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic like rustc, optionally with colours. The result doesn't end with a newline.
+    pub fn render(&self, color: bool) -> String {
+        let style = Style { color };
+
+        let mut header = style.level(self.level);
+        if let Some(code) = &self.code {
+            header += &style.paint(RED, &format!("[{}]", code.code));
+        }
+        header += &style.paint(BOLD, &format!(": {}", self.message));
+        let mut lines = vec![header];
+
+        // Gutter wide enough for the largest line number
+        let width = self
+            .spans
+            .iter()
+            .map(|span| span.line_end.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = |line: &str| style.paint(BLUE, line);
+        let empty_gutter = gutter(&format!("{} |", " ".repeat(width)));
+
+        if let Some(primary) = self.primary_span() {
+            lines.push(format!(
+                "{}{} {}:{}:{}",
+                " ".repeat(width),
+                gutter("-->"),
+                primary.file_name,
+                primary.line_start,
+                primary.column_start
+            ));
+        }
+
+        if !self.spans.is_empty() {
+            lines.push(empty_gutter.clone());
+
+            let mut previous_line = None;
+            for (line, (text, annotations)) in annotated_lines(&self.spans) {
+                // Spans only contain the lines they cover, so lines between them are elided
+                if previous_line.map_or(false, |previous| line > previous + 1) {
+                    lines.push("...".to_string());
+                }
+                previous_line = Some(line);
+
+                lines.push(source_line(&gutter, width, line, text));
+                for row in annotation_rows(&annotations, &style) {
+                    lines.push(format!("{} {}", empty_gutter, row).trim_end().to_string());
+                }
+            }
+        }
+
+        if !self.children.is_empty() {
+            if !self.spans.is_empty() {
+                lines.push(empty_gutter);
+            }
+            for child in &self.children {
+                lines.push(format!(
+                    "{}{} {}: {}",
+                    " ".repeat(width + 1),
+                    gutter("="),
+                    style.paint(BOLD, &child.level_name()),
+                    child.message
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    fn level_name(&self) -> String {
+        Style { color: false }.level(self.level)
+    }
+}
+
+fn source_line(gutter: impl Fn(&str) -> String, width: usize, line: usize, text: &str) -> String {
+    format!(
+        "{} {}",
+        gutter(&format!("{:>width$} |", line, width = width)),
+        text
+    )
+    .trim_end()
+    .to_string()
+}
+
+/// The text and annotations of every line covered by a span, by one-based line number
+fn annotated_lines(spans: &[DiagnosticSpan]) -> BTreeMap<usize, (&str, Vec<Annotation>)> {
+    let mut lines = BTreeMap::<usize, (&str, Vec<Annotation>)>::new();
+    for span in spans {
+        for (index, text) in span.text.iter().enumerate() {
+            let line = span.line_start + index;
+            let is_last = line == span.line_end;
+            let start = text.highlight_start - 1;
+            lines
+                .entry(line)
+                .or_insert_with(|| (text.text.as_str(), Vec::new()))
+                .1
+                .push(Annotation {
+                    start,
+                    // Empty spans still get a marker
+                    end: (text.highlight_end - 1).max(start + 1),
+                    is_primary: span.is_primary,
+                    label: span.label.as_deref().filter(|_| is_last),
+                });
+        }
+    }
+    for (_, annotations) in lines.values_mut() {
+        annotations.sort_by_key(|annotation| annotation.start);
+    }
+    lines
+}
+
+/// The rows below a source line: the underlines with the label of the rightmost annotation, followed by the labels
+/// of the others from right to left, each connected to its underline by `|`s
+fn annotation_rows(annotations: &[Annotation], style: &Style) -> Vec<String> {
+    let width = annotations
+        .iter()
+        .map(|annotation| annotation.end)
+        .max()
+        .unwrap_or(0);
+
+    // Secondary markers first, so primary ones win where they overlap
+    let mut underline = vec![None; width];
+    for annotation in annotations
+        .iter()
+        .filter(|annotation| !annotation.is_primary)
+        .chain(
+            annotations
+                .iter()
+                .filter(|annotation| annotation.is_primary),
+        )
+    {
+        for cell in &mut underline[annotation.start..annotation.end] {
+            *cell = Some(annotation.is_primary);
+        }
+    }
+    let mut first_row = String::new();
+    for cell in underline {
+        first_row += &match cell {
+            Some(is_primary) => style.marker(is_primary, if is_primary { "^" } else { "-" }),
+            None => " ".to_string(),
+        };
+    }
+
+    let labelled = annotations
+        .iter()
+        .filter(|annotation| annotation.label.is_some())
+        .collect::<Vec<_>>();
+    let mut rows = Vec::new();
+    let mut pending = labelled.as_slice();
+    if let Some((last, rest)) = labelled.split_last() {
+        first_row += " ";
+        first_row += &style.marker(last.is_primary, last.label.unwrap());
+        pending = rest;
+    }
+    rows.push(first_row);
+
+    while let Some((current, rest)) = pending.split_last() {
+        let connectors = |label: Option<&Annotation>| {
+            let mut row = String::new();
+            let mut column = 0;
+            for annotation in pending {
+                row += &" ".repeat(annotation.start.saturating_sub(column));
+                if label.map_or(false, |label| std::ptr::eq(label, *annotation)) {
+                    row += &style.marker(annotation.is_primary, annotation.label.unwrap());
+                    break;
+                }
+                row += &style.marker(annotation.is_primary, "|");
+                column = annotation.start + 1;
+            }
+            row
+        };
+        rows.push(connectors(None));
+        rows.push(connectors(Some(current)));
+        pending = rest;
+    }
+
+    rows
+}
@@ -0,0 +1,254 @@
+//! SARIF 2.1.0 logs for code scanning tools, serialized from the rustc diagnostics of the checked files
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{CheckerErrorKind, DiagnosticSpan, FileReport};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A SARIF log with a single run, which reports every checked file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: &'static str,
+    pub version: &'static str,
+    pub runs: Vec<Run>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Run {
+    pub tool: Tool,
+    pub invocations: Vec<Invocation>,
+    pub results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tool {
+    pub driver: Driver,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Driver {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// One rule per `CheckerErrorKind`, in the order of `CheckerErrorKind::ALL`
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    /// The name of the `CheckerError` variant
+    pub id: &'static str,
+    pub short_description: Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invocation {
+    /// Whether every file could be checked
+    pub execution_successful: bool,
+    /// Files that couldn't be checked, e.g. because they contain code the checker doesn't support yet
+    pub tool_execution_notifications: Vec<Notification>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    pub level: &'static str,
+    pub message: Message,
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    pub rule_id: &'static str,
+    pub rule_index: usize,
+    pub level: &'static str,
+    pub message: Message,
+    /// Where the error has been reported
+    pub locations: Vec<Location>,
+    /// The other labelled spans of the error, e.g. the borrow that has been invalidated and the later use of it
+    pub related_locations: Vec<Location>,
+    pub properties: ResultProperties,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResultProperties {
+    /// The code of the matching rustc error (e.g. `E0499`)
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Message {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    /// Only set on related locations, which are numbered from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+    pub physical_location: PhysicalLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhysicalLocation {
+    pub artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtifactLocation {
+    pub uri: String,
+}
+
+/// Lines and columns are one-based, columns count Unicode code points (SARIF's default) and the end is exclusive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Region {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl SarifLog {
+    /// Logs the errors of `reports` as results and the files in `unchecked` as failed executions
+    pub fn new(reports: &[FileReport], unchecked: &[PathBuf]) -> Self {
+        let mut results = Vec::new();
+        let mut notifications = unchecked
+            .iter()
+            .map(|path| {
+                Notification::error(
+                    path,
+                    "contains code the checker doesn't support yet".to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for report in reports {
+            let error = match &report.result {
+                Ok(()) => continue,
+                Err(error) => error,
+            };
+            let kind = match error.kind() {
+                Some(kind) => kind,
+                None => {
+                    notifications.push(Notification::error(&report.path, error.to_string()));
+                    continue;
+                }
+            };
+
+            let diagnostic = error.diagnostic(&uri(&report.path), &report.source);
+            let (primary, related): (Vec<_>, Vec<_>) =
+                diagnostic.spans.iter().partition(|span| span.is_primary);
+            results.push(SarifResult {
+                rule_id: kind.name(),
+                rule_index: rule_index(kind),
+                level: "error",
+                message: Message::new(diagnostic.message.clone()),
+                locations: primary
+                    .into_iter()
+                    .map(|span| Location::new(None, span))
+                    .collect(),
+                related_locations: related
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, span)| Location::new(Some(index + 1), span))
+                    .collect(),
+                properties: ResultProperties {
+                    code: diagnostic.code.as_ref().map(|code| code.code.clone()),
+                },
+            });
+        }
+
+        Self {
+            schema: SCHEMA,
+            version: "2.1.0",
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "lifetimes",
+                        version: env!("CARGO_PKG_VERSION"),
+                        rules: CheckerErrorKind::ALL
+                            .iter()
+                            .map(|kind| Rule {
+                                id: kind.name(),
+                                short_description: Message::new(kind.description().to_string()),
+                            })
+                            .collect(),
+                    },
+                },
+                invocations: vec![Invocation {
+                    execution_successful: notifications.is_empty(),
+                    tool_execution_notifications: notifications,
+                }],
+                results,
+            }],
+        }
+    }
+}
+
+impl Notification {
+    fn error(path: &Path, message: String) -> Self {
+        Self {
+            level: "error",
+            message: Message::new(message),
+            locations: vec![Location {
+                id: None,
+                physical_location: PhysicalLocation {
+                    artifact_location: ArtifactLocation { uri: uri(path) },
+                    region: None,
+                },
+                message: None,
+            }],
+        }
+    }
+}
+
+impl Message {
+    fn new(text: String) -> Self {
+        Self { text }
+    }
+}
+
+impl Location {
+    fn new(id: Option<usize>, span: &DiagnosticSpan) -> Self {
+        Self {
+            id,
+            physical_location: PhysicalLocation {
+                artifact_location: ArtifactLocation {
+                    uri: span.file_name.clone(),
+                },
+                region: Some(Region {
+                    start_line: span.line_start,
+                    start_column: span.column_start,
+                    end_line: span.line_end,
+                    end_column: span.column_end,
+                }),
+            },
+            message: span.label.clone().map(Message::new),
+        }
+    }
+}
+
+fn rule_index(kind: CheckerErrorKind) -> usize {
+    CheckerErrorKind::ALL
+        .iter()
+        .position(|rule| *rule == kind)
+        .unwrap()
+}
+
+/// Relative paths are kept relative (to be resolved against the checkout), with `/` as separator
+fn uri(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
@@ -0,0 +1,303 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{
+        hash_map::{DefaultHasher, Entry},
+        HashMap, HashSet,
+    },
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use base_db::SourceDatabaseExt;
+use hir::{db::HirDatabase, Semantics};
+use ide::{AnalysisHost, Change, FileId};
+use syntax::{
+    ast::{self, AstNode, HasName},
+    NodeOrToken, SyntaxNode, TextRange,
+};
+
+use crate::{
+    check_each_function, check_function, checked_functions, checker::Checker, line_of,
+    standalone_crates, CheckError, CheckerError, FunctionLifetimes, FunctionOrigins, FunctionTrace,
+    MAIN_PATH,
+};
+
+/// Keeps the analysis of a set of files alive between checks, so editing a file doesn't start from scratch.
+/// Every file is the root of its own crate.
+///
+/// The results of `check` and `lifetimes` are cached per function, keyed by the function's text, the
+/// signatures of the functions it calls and the other items of the file (structs, statics, impl blocks, ...).
+/// Only functions whose key changed are checked again.
+pub struct Session {
+    host: AnalysisHost,
+    files: HashMap<String, FileId>,
+    /// Ids of removed files aren't reused, so no stale cache entry or analysis result can refer to a new file
+    next_file: u32,
+    cache: RefCell<HashMap<FileId, HashMap<u64, CheckedFunction>>>,
+    functions_checked: Cell<usize>,
+}
+
+/// The result of checking a function, with ranges and lines relative to the start of the function,
+/// so it stays valid when the function is moved by edits above it
+struct CheckedFunction {
+    lifetimes: FunctionLifetimes,
+    /// Only borrowcheck errors are cached, as they (unlike `CheckError`s) can be cloned. Other errors of
+    /// `check_function` (e.g. unsupported syntax) make the function be checked again.
+    error: Option<(CheckerError, TextRange)>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            host: AnalysisHost::new(None),
+            files: HashMap::new(),
+            next_file: 0,
+            cache: RefCell::new(HashMap::new()),
+            functions_checked: Cell::new(0),
+        }
+    }
+
+    /// A session containing only `code`, at the path used by `check`
+    pub(crate) fn with_code(code: String) -> Self {
+        let mut session = Self::new();
+        session.set_file(MAIN_PATH, code);
+        session
+    }
+
+    /// Opens the file at the virtual `path` (which has to start with `/`) or replaces its text if it is open already
+    pub fn set_file(&mut self, path: &str, code: String) {
+        let (file, mut change) = match self.files.get(path) {
+            Some(file) => (*file, Change::default()),
+            None => {
+                let file = FileId(self.next_file);
+                self.next_file += 1;
+                self.files.insert(path.to_string(), file);
+                // The crate graph contains all files, so it is rebuilt whenever a file is added
+                let change =
+                    standalone_crates(self.files.iter().map(|(path, file)| (*file, path.as_str())));
+                (file, change)
+            }
+        };
+        change.change_file(file, Some(Arc::new(code)));
+        self.host.apply_change(change);
+    }
+
+    /// Closes the file at `path`, dropping its cached results. Does nothing if it isn't open.
+    pub fn remove_file(&mut self, path: &str) {
+        let file = match self.files.remove(path) {
+            Some(file) => file,
+            None => return,
+        };
+        self.cache.borrow_mut().remove(&file);
+
+        let mut change =
+            standalone_crates(self.files.iter().map(|(path, file)| (*file, path.as_str())));
+        change.change_file(file, None);
+        self.host.apply_change(change);
+    }
+
+    pub fn contains_file(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// The current text of the file at `path`, which must have been opened with `set_file`
+    pub fn text(&self, path: &str) -> Arc<String> {
+        self.host.raw_database().file_text(self.file(path))
+    }
+
+    /// Checks every function of the file at `path`, which must have been opened with `set_file`
+    pub fn check(&self, path: &str) -> Result<(), CheckError> {
+        self.lifetimes(path).1
+    }
+
+    /// See `lifetimes_backend::check_with_trace`. Traces are not cached, so this always checks every function.
+    pub fn check_with_trace(&self, path: &str) -> (Vec<FunctionTrace>, Result<(), CheckError>) {
+        let semantics = Semantics::new(self.host.raw_database());
+        check_each_function(
+            &semantics,
+            self.file(path),
+            Checker::enable_trace,
+            |function, checker| FunctionTrace {
+                name: function.name().unwrap().text().to_string(),
+                statements: checker.take_trace(),
+            },
+        )
+    }
+
+    /// See `lifetimes_backend::origins`. Like traces, origins are not cached.
+    pub fn origins(&self, path: &str) -> (Vec<FunctionOrigins>, Result<(), CheckError>) {
+        let semantics = Semantics::new(self.host.raw_database());
+        check_each_function(
+            &semantics,
+            self.file(path),
+            |_| {},
+            |function, checker| FunctionOrigins {
+                name: function.name().unwrap().text().to_string(),
+                origins: checker.origins(),
+                vars: checker.all_vars(),
+            },
+        )
+    }
+
+    /// See `lifetimes_backend::lifetimes`
+    pub fn lifetimes(&self, path: &str) -> (Vec<FunctionLifetimes>, Result<(), CheckError>) {
+        let file = self.file(path);
+        let source = self.text(path);
+        let semantics = Semantics::new(self.host.raw_database());
+
+        let mut cache = self.cache.borrow_mut();
+        let cached_functions = cache.entry(file).or_default();
+        let mut keys = HashSet::new();
+
+        let file_node = semantics.parse(file);
+        let items = items_key(&file_node);
+        let mut lifetimes = Vec::new();
+        let mut result = Ok(());
+        for function in checked_functions(&file_node) {
+            let key = function_key(&function, items, &semantics);
+            keys.insert(key);
+
+            let start = function.syntax().text_range().start();
+            let start_line = line_of(&source, function.syntax().text_range()) - 1;
+            let checked = match cached_functions.entry(key) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    self.functions_checked.set(self.functions_checked.get() + 1);
+
+                    let mut checker = Checker::new();
+                    let checked = check_function(&function, &mut checker, &semantics);
+                    let function_lifetimes = FunctionLifetimes::new(
+                        function.name().unwrap().text().to_string(),
+                        &checker.all_vars(),
+                        &checker.loans(),
+                        &source,
+                    );
+                    let error = match checked {
+                        Ok(()) => None,
+                        Err(CheckError::Borrowcheck { error, range }) => Some((
+                            error
+                                .map_ranges(|range| range - start)
+                                .map_lines(|line| line - start_line),
+                            range - start,
+                        )),
+                        // Other errors (e.g. unsupported syntax) aren't cached, the function is checked again
+                        Err(error) => {
+                            lifetimes.push(function_lifetimes);
+                            result = Err(error);
+                            break;
+                        }
+                    };
+                    entry.insert(CheckedFunction {
+                        lifetimes: function_lifetimes.map_lines(|line| line - start_line),
+                        error,
+                    })
+                }
+            };
+
+            lifetimes.push(
+                checked
+                    .lifetimes
+                    .clone()
+                    .map_lines(|line| line + start_line),
+            );
+            if let Some((error, range)) = &checked.error {
+                result = Err(CheckError::Borrowcheck {
+                    error: error.clone().map_lines(|line| line + start_line),
+                    range: *range,
+                }
+                .map_ranges(|range| range + start));
+                break;
+            }
+        }
+
+        // Functions that have been edited or removed won't come back with the same key. If checking stopped at an
+        // error, the functions after it haven't been visited, so their results are kept.
+        if result.is_ok() {
+            cached_functions.retain(|key, _| keys.contains(key));
+        }
+
+        (lifetimes, result)
+    }
+
+    /// How many functions have been checked so far. Functions whose results were cached are not counted.
+    pub fn functions_checked(&self) -> usize {
+        self.functions_checked.get()
+    }
+
+    fn file(&self, path: &str) -> FileId {
+        *self
+            .files
+            .get(path)
+            .unwrap_or_else(|| panic!("{} has not been opened", path))
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Identifies the version of a function that has been checked: its text, the signatures of the functions it calls
+/// and the `items` of the file
+fn function_key<'db, DB: HirDatabase>(
+    function: &ast::Fn,
+    items: u64,
+    sema: &Semantics<'db, DB>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    function.syntax().to_string().hash(&mut hasher);
+    items.hash(&mut hasher);
+
+    for node in function.syntax().descendants() {
+        let callee = if let Some(call) = ast::CallExpr::cast(node.clone()) {
+            match call.expr() {
+                Some(ast::Expr::PathExpr(path)) => match sema.resolve_path(&path.path().unwrap()) {
+                    Some(hir::PathResolution::Def(hir::ModuleDef::Function(callee))) => {
+                        Some(callee)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            }
+        } else if let Some(call) = ast::MethodCallExpr::cast(node) {
+            sema.resolve_method_call(&call)
+        } else {
+            None
+        };
+
+        if let Some(callee) = callee.and_then(|callee| sema.source(callee)) {
+            signature(&callee.value).hash(&mut hasher);
+        }
+    }
+
+    hasher.finish()
+}
+
+/// Identifies the items besides functions that a function may resolve, like structs, statics and the generics of
+/// impl blocks: the tokens of the file outside of functions. Whitespace and comments are left out, so moving a
+/// function keeps the results of the others.
+fn items_key(file: &ast::SourceFile) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_items(file.syntax(), &mut hasher);
+    hasher.finish()
+}
+
+fn hash_items(node: &SyntaxNode, hasher: &mut DefaultHasher) {
+    for child in node.children_with_tokens() {
+        match child {
+            NodeOrToken::Node(node) if !ast::Fn::can_cast(node.kind()) => hash_items(&node, hasher),
+            NodeOrToken::Token(token) if !token.kind().is_trivia() => token.text().hash(hasher),
+            _ => {}
+        }
+    }
+}
+
+/// The text of a function without its body
+fn signature(function: &ast::Fn) -> String {
+    let range = function.syntax().text_range();
+    let end = function
+        .body()
+        .map_or(range.end(), |body| body.syntax().text_range().start());
+    function.syntax().to_string()[..usize::from(end - range.start())].to_string()
+}
@@ -0,0 +1,395 @@
+//! The signatures of called functions. Their generic parameters are instantiated afresh at every call: at a call, a
+//! parameter stands for the borrows of the arguments whose types mention it, which tells which arguments the result
+//! may hold and which arguments the callee may store in the others.
+
+use hir::{db::HirDatabase, Semantics};
+use syntax::{
+    ast::{self, AstNode, HasGenericParams, HasName, HasTypeBounds},
+    SyntaxNode,
+};
+
+use crate::variance::{self, is_lifetime, mentions, Variance};
+
+pub(crate) struct Signature {
+    /// The receiver of a method is its first parameter
+    params: Vec<Param>,
+    ret_type: Option<ast::Type>,
+    generics: Generics,
+    /// The type of the `impl` block, which `Self` stands for
+    self_ty: Option<ast::Type>,
+}
+
+impl Signature {
+    pub(crate) fn of<'db, DB: HirDatabase>(
+        function: hir::Function,
+        sema: &Semantics<'db, DB>,
+    ) -> Option<Self> {
+        let function = sema.source(function)?.value;
+        let self_ty = function
+            .syntax()
+            .ancestors()
+            .find_map(ast::Impl::cast)
+            .and_then(|implementation| implementation.self_ty());
+
+        let mut params = Vec::new();
+        if let Some(param_list) = function.param_list() {
+            if let Some(self_param) = param_list.self_param() {
+                params.push(Param::SelfParam(self_param, self_ty.clone()));
+            }
+            params.extend(
+                param_list
+                    .params()
+                    .filter_map(|param| param.ty())
+                    .map(Param::Type),
+            );
+        }
+
+        Some(Self {
+            params,
+            ret_type: function.ret_type().and_then(|ret_type| ret_type.ty()),
+            generics: Generics::of(&function),
+            self_ty,
+        })
+    }
+
+    /// The indices of the arguments whose borrows the result may hold: the arguments mentioning a generic parameter
+    /// of the return type, or one declared to outlive it (`'b: 'a` or `T: 'a`). `None` if they can't be told apart,
+    /// i.e. if the return type has elided lifetimes and the receiver isn't a reference.
+    pub(crate) fn returned_args(&self) -> Option<Vec<usize>> {
+        let ret_type = match &self.ret_type {
+            Some(ret_type) => ret_type,
+            None => return Some(Vec::new()),
+        };
+
+        let mut returned = self
+            .generics
+            .params
+            .iter()
+            .filter(|param| mentions(param, ret_type.syntax()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(self_ty) = self
+            .self_ty
+            .as_ref()
+            .filter(|_| mentions("Self", ret_type.syntax()))
+        {
+            returned.extend(
+                self.generics
+                    .params
+                    .iter()
+                    .filter(|param| mentions(param, self_ty.syntax()))
+                    .cloned(),
+            );
+        }
+        loop {
+            let longer = self
+                .generics
+                .outlives
+                .iter()
+                .filter(|(longer, shorter)| {
+                    returned.contains(shorter) && !returned.contains(longer)
+                })
+                .map(|(longer, _)| longer.clone())
+                .collect::<Vec<_>>();
+            if longer.is_empty() {
+                break;
+            }
+            returned.extend(longer);
+        }
+
+        let mut args = (0..self.params.len())
+            .filter(|index| {
+                returned
+                    .iter()
+                    .any(|param| self.params[*index].mentions(param))
+            })
+            .collect::<Vec<_>>();
+
+        let has_elided_lifetimes = ret_type.syntax().descendants().any(|node| {
+            ast::RefType::cast(node.clone()).map_or(false, |ty| ty.lifetime().is_none())
+                || ast::Lifetime::cast(node).map_or(false, |lifetime| lifetime.to_string() == "'_")
+        });
+        if has_elided_lifetimes {
+            // The elided lifetimes of the result are the receiver's, if it is a reference
+            match self.params.first() {
+                Some(Param::SelfParam(self_param, _)) if self_param.amp_token().is_some() => {
+                    if !args.contains(&0) {
+                        args.insert(0, 0);
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(args)
+    }
+
+    /// The indices of the arguments that may only borrow `'static` data: those whose parameter's type mentions
+    /// `'static` or a generic parameter bounded by it (e.g. `F: 'static` on `std::thread::spawn`)
+    pub(crate) fn static_args(&self) -> Vec<usize> {
+        let bounded = self
+            .generics
+            .outlives
+            .iter()
+            .filter(|(_, shorter)| shorter == "'static")
+            .map(|(longer, _)| longer.as_str())
+            .chain(std::iter::once("'static"))
+            .collect::<Vec<_>>();
+        (0..self.params.len())
+            .filter(|index| {
+                bounded
+                    .iter()
+                    .any(|param| self.params[*index].mentions(param))
+            })
+            .collect()
+    }
+
+    /// The pairs of argument indices `(into, from)` for which the callee may store the argument `from` in the places
+    /// borrowed by the argument `into`, because they mention a lifetime parameter of the signature in which the type
+    /// of `into` is invariant
+    pub(crate) fn invariant_flows<'db, DB: HirDatabase>(
+        &self,
+        sema: &Semantics<'db, DB>,
+    ) -> Vec<(usize, usize)> {
+        let mut flows = Vec::new();
+        for lifetime in self.generics.lifetimes() {
+            for (into, param) in self.params.iter().enumerate() {
+                if param.variance(lifetime, sema) != Variance::Invariant {
+                    continue;
+                }
+                for (from, other) in self.params.iter().enumerate() {
+                    if from != into && other.mentions(lifetime) && !flows.contains(&(into, from)) {
+                        flows.push((into, from));
+                    }
+                }
+            }
+        }
+        flows
+    }
+}
+
+/// The lifetime bound of the opaque type or the trait object a function returns, which the returned value may only
+/// hold data of
+pub(crate) enum ReturnBound {
+    /// `impl Trait + 'a`, which captures the lifetimes its bounds mention
+    Opaque { ty: String, captured: Vec<String> },
+    /// `dyn Trait + 'a` behind a pointer. An elided bound defaults to the lifetime of the reference the object is
+    /// behind (`&'a dyn Trait`), else to `'static` (`Box<dyn Trait>`).
+    Object { lifetime: String },
+}
+
+impl ReturnBound {
+    /// `None` if the return type `ty` is neither an opaque type nor holds a trait object
+    pub(crate) fn of(ty: &ast::Type) -> Option<Self> {
+        match ty {
+            ast::Type::ParenType(ty) => ty.ty().as_ref().and_then(Self::of),
+            ast::Type::ImplTraitType(opaque) => {
+                let mut captured = Vec::new();
+                for lifetime in opaque
+                    .syntax()
+                    .descendants()
+                    .filter_map(ast::Lifetime::cast)
+                {
+                    let lifetime = lifetime.to_string();
+                    if !captured.contains(&lifetime) {
+                        captured.push(lifetime);
+                    }
+                }
+                Some(ReturnBound::Opaque {
+                    ty: opaque.syntax().text().to_string(),
+                    captured,
+                })
+            }
+            _ => {
+                let object = ty
+                    .syntax()
+                    .descendants()
+                    .find_map(ast::DynTraitType::cast)?;
+                let bound = object
+                    .type_bound_list()
+                    .into_iter()
+                    .flat_map(|bounds| bounds.bounds())
+                    .find_map(|bound| bound.lifetime());
+                let lifetime = match bound {
+                    Some(lifetime) => lifetime.to_string(),
+                    None => match object.syntax().parent().and_then(ast::RefType::cast) {
+                        Some(reference) => reference
+                            .lifetime()
+                            .map_or_else(|| "'_".to_string(), |lifetime| lifetime.to_string()),
+                        None => "'static".to_string(),
+                    },
+                };
+                Some(ReturnBound::Object { lifetime })
+            }
+        }
+    }
+}
+
+/// The lifetimes the type of a parameter mentions, except `'static`. Every elided lifetime is a distinct `'_`.
+pub(crate) fn param_lifetimes(ty: &ast::Type) -> Vec<String> {
+    let mut lifetimes = Vec::new();
+    for node in ty.syntax().descendants() {
+        let lifetime = match (ast::RefType::cast(node.clone()), ast::Lifetime::cast(node)) {
+            (Some(reference), _) if reference.lifetime().is_none() => "'_".to_string(),
+            (_, Some(lifetime)) => lifetime.to_string(),
+            _ => continue,
+        };
+        if lifetime == "'_" || (lifetime != "'static" && !lifetimes.contains(&lifetime)) {
+            lifetimes.push(lifetime);
+        }
+    }
+    lifetimes
+}
+
+/// The generic parameters of a function and of its `impl` block, with their declared bounds
+#[derive(Default)]
+pub(crate) struct Generics {
+    /// Lifetime parameters (e.g. `'a`) and type parameters (e.g. `T`)
+    params: Vec<String>,
+    /// `(longer, shorter)` for the bounds `'a: 'b` and `T: 'b`
+    outlives: Vec<(String, String)>,
+    /// The type parameters bounded by `Copy`
+    copy: Vec<String>,
+}
+
+impl Generics {
+    pub(crate) fn of(function: &ast::Fn) -> Self {
+        let mut generics = Self::default();
+        generics.add(function.generic_param_list(), function.where_clause());
+        if let Some(implementation) = function.syntax().ancestors().find_map(ast::Impl::cast) {
+            generics.add(
+                implementation.generic_param_list(),
+                implementation.where_clause(),
+            );
+        }
+        generics
+    }
+
+    /// The generics of the function `node` is in, none outside of functions
+    pub(crate) fn enclosing(node: &SyntaxNode) -> Self {
+        node.ancestors()
+            .find_map(ast::Fn::cast)
+            .map_or_else(Generics::default, |function| Generics::of(&function))
+    }
+
+    /// Whether the type parameter `param` is bounded by `Copy`. Without a sysroot, the type of its values doesn't
+    /// tell.
+    pub(crate) fn is_copy(&self, param: &str) -> bool {
+        self.copy.iter().any(|copy| copy == param)
+    }
+
+    fn lifetimes(&self) -> impl Iterator<Item = &String> {
+        self.params.iter().filter(|param| param.starts_with('\''))
+    }
+
+    fn add(
+        &mut self,
+        params: Option<ast::GenericParamList>,
+        where_clause: Option<ast::WhereClause>,
+    ) {
+        for param in params.iter().flat_map(|params| params.generic_params()) {
+            let (name, bounds) = match param {
+                ast::GenericParam::LifetimeParam(param) => (
+                    param.lifetime().map(|lifetime| lifetime.to_string()),
+                    param.type_bound_list(),
+                ),
+                ast::GenericParam::TypeParam(param) => (
+                    param.name().map(|name| name.to_string()),
+                    param.type_bound_list(),
+                ),
+                ast::GenericParam::ConstParam(_) => continue,
+            };
+            if let Some(name) = name {
+                self.add_bounds(&name, bounds);
+                self.params.push(name);
+            }
+        }
+
+        for predicate in where_clause
+            .iter()
+            .flat_map(|where_clause| where_clause.predicates())
+        {
+            let bounded = predicate
+                .lifetime()
+                .map(|lifetime| lifetime.to_string())
+                .or_else(|| predicate.ty().map(|ty| ty.syntax().text().to_string()));
+            if let Some(bounded) = bounded {
+                self.add_bounds(&bounded, predicate.type_bound_list());
+            }
+        }
+    }
+
+    fn add_bounds(&mut self, bounded: &str, bounds: Option<ast::TypeBoundList>) {
+        for bound in bounds.iter().flat_map(|bounds| bounds.bounds()) {
+            if let Some(lifetime) = bound.lifetime() {
+                self.outlives
+                    .push((bounded.to_string(), lifetime.to_string()));
+            } else if is_copy_bound(&bound) {
+                self.copy.push(bounded.to_string());
+            }
+        }
+    }
+}
+
+/// Whether `bound` is the `Copy` trait, e.g. `Copy` or `std::marker::Copy`
+fn is_copy_bound(bound: &ast::TypeBound) -> bool {
+    match bound.ty() {
+        Some(ast::Type::PathType(ty)) => ty
+            .path()
+            .and_then(|path| path.segment())
+            .and_then(|segment| segment.name_ref())
+            .map_or(false, |name| name.text() == "Copy"),
+        _ => false,
+    }
+}
+
+/// The type of a function's parameter
+enum Param {
+    Type(ast::Type),
+    /// With the type of the `impl` block
+    SelfParam(ast::SelfParam, Option<ast::Type>),
+}
+
+impl Param {
+    fn variance<'db, DB: HirDatabase>(&self, param: &str, sema: &Semantics<'db, DB>) -> Variance {
+        match self {
+            Param::Type(ty) => variance::variance(param, ty, sema),
+            Param::SelfParam(self_param, self_ty) => {
+                if let Some(ty) = self_param.ty() {
+                    return variance::variance(param, &ty, sema);
+                }
+                let self_variance = self_ty.as_ref().map_or(Variance::Bivariant, |ty| {
+                    variance::variance(param, ty, sema)
+                });
+                if self_param.amp_token().is_none() {
+                    return self_variance;
+                }
+                let lifetime_variance = if is_lifetime(self_param.lifetime(), param) {
+                    Variance::Covariant
+                } else {
+                    Variance::Bivariant
+                };
+                let pointee = if self_param.mut_token().is_some() {
+                    Variance::Invariant
+                } else {
+                    Variance::Covariant
+                };
+                lifetime_variance.join(pointee.xform(self_variance))
+            }
+        }
+    }
+
+    fn mentions(&self, param: &str) -> bool {
+        match self {
+            Param::Type(ty) => mentions(param, ty.syntax()),
+            Param::SelfParam(self_param, self_ty) => match self_param.ty() {
+                Some(ty) => mentions(param, ty.syntax()),
+                None => {
+                    is_lifetime(self_param.lifetime(), param)
+                        || self_ty
+                            .as_ref()
+                            .map_or(false, |ty| mentions(param, ty.syntax()))
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Variance of lifetime parameters, computed from the definitions of the types that mention them like rustc does.
+//! A function whose signature is invariant in a lifetime parameter may store the arguments mentioning it in the
+//! places borrowed by the invariant argument (e.g. `*r = v` for `r: &mut &'a T, v: &'a T`), see `signature`.
+
+use hir::{db::HirDatabase, Semantics};
+use syntax::ast::{self, AstNode, HasAttrs, HasGenericParams, HasName};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Variance {
+    Covariant,
+    Contravariant,
+    Invariant,
+    /// The parameter isn't mentioned
+    Bivariant,
+}
+
+impl Variance {
+    /// The variance of an occurrence with the variance `inner` in a position with the variance `self`
+    pub(crate) fn xform(self, inner: Variance) -> Variance {
+        match (self, inner) {
+            (Variance::Bivariant, _) | (_, Variance::Bivariant) => Variance::Bivariant,
+            (Variance::Covariant, inner) => inner,
+            (Variance::Contravariant, Variance::Covariant) => Variance::Contravariant,
+            (Variance::Contravariant, Variance::Contravariant) => Variance::Covariant,
+            (Variance::Contravariant, Variance::Invariant) | (Variance::Invariant, _) => {
+                Variance::Invariant
+            }
+        }
+    }
+
+    /// The variance of a parameter that occurs with both variances
+    pub(crate) fn join(self, other: Variance) -> Variance {
+        match (self, other) {
+            (Variance::Bivariant, other) => other,
+            (this, Variance::Bivariant) => this,
+            (this, other) if this == other => this,
+            _ => Variance::Invariant,
+        }
+    }
+}
+
+/// The variance of the generic parameter `param` (e.g. `'a` or `T`) in `ty`
+pub(crate) fn variance<'db, DB: HirDatabase>(
+    param: &str,
+    ty: &ast::Type,
+    sema: &Semantics<'db, DB>,
+) -> Variance {
+    variance_in(param, ty, sema, &mut Vec::new())
+}
+
+/// `visiting` are the type parameters whose variance is being computed, to stop at recursive types
+fn variance_in<'db, DB: HirDatabase>(
+    param: &str,
+    ty: &ast::Type,
+    sema: &Semantics<'db, DB>,
+    visiting: &mut Vec<(hir::Adt, usize)>,
+) -> Variance {
+    let inner = |ty: Option<ast::Type>, visiting: &mut Vec<(hir::Adt, usize)>| {
+        ty.map_or(Variance::Bivariant, |ty| {
+            variance_in(param, &ty, sema, visiting)
+        })
+    };
+
+    match ty {
+        ast::Type::RefType(ty) => {
+            let lifetime = if is_lifetime(ty.lifetime(), param) {
+                Variance::Covariant
+            } else {
+                Variance::Bivariant
+            };
+            // `&'a mut T` is invariant in `T`
+            let pointee = if ty.mut_token().is_some() {
+                Variance::Invariant
+            } else {
+                Variance::Covariant
+            };
+            lifetime.join(pointee.xform(inner(ty.ty(), visiting)))
+        }
+        ast::Type::PtrType(ty) if ty.mut_token().is_some() => {
+            Variance::Invariant.xform(inner(ty.ty(), visiting))
+        }
+        ast::Type::PtrType(ty) => inner(ty.ty(), visiting),
+        ast::Type::ArrayType(ty) => inner(ty.ty(), visiting),
+        ast::Type::SliceType(ty) => inner(ty.ty(), visiting),
+        ast::Type::ParenType(ty) => inner(ty.ty(), visiting),
+        ast::Type::TupleType(ty) => ty
+            .fields()
+            .map(|field| variance_in(param, &field, sema, visiting))
+            .fold(Variance::Bivariant, Variance::join),
+        // Functions are contravariant in their parameters
+        ast::Type::FnPtrType(ty) => {
+            let params = ty
+                .param_list()
+                .into_iter()
+                .flat_map(|params| params.params())
+                .map(|fn_param| Variance::Contravariant.xform(inner(fn_param.ty(), visiting)))
+                .fold(Variance::Bivariant, Variance::join);
+            params.join(inner(ty.ret_type().and_then(|ret| ret.ty()), visiting))
+        }
+        ast::Type::PathType(ty) => match ty.path() {
+            Some(path) => variance_in_path(param, &path, sema, visiting),
+            None => Variance::Bivariant,
+        },
+        // The lifetime bound of a trait object is covariant, the arguments of its traits are invariant
+        ast::Type::DynTraitType(_) | ast::Type::ImplTraitType(_) => ty
+            .syntax()
+            .children()
+            .filter_map(ast::TypeBoundList::cast)
+            .flat_map(|bounds| bounds.bounds())
+            .map(|bound| {
+                if is_lifetime(bound.lifetime(), param) {
+                    Variance::Covariant
+                } else if mentions(param, bound.syntax()) {
+                    Variance::Invariant
+                } else {
+                    Variance::Bivariant
+                }
+            })
+            .fold(Variance::Bivariant, Variance::join),
+        _ if mentions(param, ty.syntax()) => Variance::Invariant,
+        _ => Variance::Bivariant,
+    }
+}
+
+/// The variance of `param` in a path type: the type parameter itself, or a struct or an enum whose generic
+/// arguments mention it. Unknown types and `UnsafeCell` are assumed to be invariant in all the parameters they
+/// mention.
+fn variance_in_path<'db, DB: HirDatabase>(
+    param: &str,
+    path: &ast::Path,
+    sema: &Semantics<'db, DB>,
+    visiting: &mut Vec<(hir::Adt, usize)>,
+) -> Variance {
+    if path.qualifier().is_none() && path.syntax().text() == param {
+        return Variance::Covariant;
+    }
+    let adt = match sema.resolve_path(path) {
+        Some(hir::PathResolution::Def(hir::ModuleDef::Adt(adt))) => adt,
+        _ if mentions(param, path.syntax()) => return Variance::Invariant,
+        _ => return Variance::Bivariant,
+    };
+    // The field of `UnsafeCell<T>` is a plain `T`, but it can be written through a shared reference. That makes it
+    // and the types built on it (`Cell<T>`, `RefCell<T>`, `Mutex<T>`...) invariant in `T`.
+    if is_unsafe_cell(adt, sema) {
+        return if mentions(param, path.syntax()) {
+            Variance::Invariant
+        } else {
+            Variance::Bivariant
+        };
+    }
+    let (generics, field_types) = match adt_definition(adt, sema) {
+        Some(definition) => definition,
+        None if mentions(param, path.syntax()) => return Variance::Invariant,
+        None => return Variance::Bivariant,
+    };
+    let generics = generics
+        .iter()
+        .flat_map(|generics| generics.generic_params())
+        .collect::<Vec<_>>();
+
+    let mut lifetime_args = 0;
+    let mut type_args = 0;
+    let mut variance = Variance::Bivariant;
+    let args = path
+        .segment()
+        .and_then(|segment| segment.generic_arg_list())
+        .into_iter()
+        .flat_map(|args| args.generic_args());
+    for arg in args {
+        // The `n`th lifetime argument is the `n`th lifetime parameter of the type, same for type arguments
+        let (arg_variance, nth_param) = match &arg {
+            ast::GenericArg::LifetimeArg(arg) => {
+                let nth_param = generics
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, param)| matches!(param, ast::GenericParam::LifetimeParam(_)))
+                    .nth(lifetime_args);
+                lifetime_args += 1;
+                let arg_variance = if is_lifetime(arg.lifetime(), param) {
+                    Variance::Covariant
+                } else {
+                    Variance::Bivariant
+                };
+                (arg_variance, nth_param)
+            }
+            ast::GenericArg::TypeArg(arg) => {
+                let nth_param = generics
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, param)| matches!(param, ast::GenericParam::TypeParam(_)))
+                    .nth(type_args);
+                type_args += 1;
+                let arg_variance = arg.ty().map_or(Variance::Bivariant, |ty| {
+                    variance_in(param, &ty, sema, visiting)
+                });
+                (arg_variance, nth_param)
+            }
+            arg if mentions(param, arg.syntax()) => (Variance::Invariant, None),
+            _ => (Variance::Bivariant, None),
+        };
+        if arg_variance == Variance::Bivariant {
+            continue;
+        }
+
+        let param_variance = match nth_param {
+            Some((index, generic_param)) => {
+                if visiting.contains(&(adt, index)) {
+                    // Recursive types don't add occurrences of their own parameters
+                    continue;
+                }
+                let name = match generic_param {
+                    ast::GenericParam::LifetimeParam(param) => {
+                        param.lifetime().map(|lifetime| lifetime.to_string())
+                    }
+                    ast::GenericParam::TypeParam(param) => {
+                        param.name().map(|name| name.to_string())
+                    }
+                    ast::GenericParam::ConstParam(_) => None,
+                };
+                visiting.push((adt, index));
+                let param_variance = name.map_or(Variance::Invariant, |name| {
+                    field_types
+                        .iter()
+                        .map(|ty| variance_in(&name, ty, sema, visiting))
+                        .fold(Variance::Bivariant, Variance::join)
+                });
+                visiting.pop();
+                param_variance
+            }
+            None => Variance::Invariant,
+        };
+        variance = variance.join(param_variance.xform(arg_variance));
+    }
+    variance
+}
+
+/// The generic parameters of a struct, an enum or a union, and the types of all its fields
+fn adt_definition<'db, DB: HirDatabase>(
+    adt: hir::Adt,
+    sema: &Semantics<'db, DB>,
+) -> Option<(Option<ast::GenericParamList>, Vec<ast::Type>)> {
+    let (generics, field_lists) = match adt {
+        hir::Adt::Struct(def) => {
+            let def = sema.source(def)?.value;
+            (
+                def.generic_param_list(),
+                def.field_list().into_iter().collect::<Vec<_>>(),
+            )
+        }
+        hir::Adt::Enum(def) => {
+            let def = sema.source(def)?.value;
+            let field_lists = def
+                .variant_list()
+                .into_iter()
+                .flat_map(|variants| variants.variants())
+                .filter_map(|variant| variant.field_list())
+                .collect();
+            (def.generic_param_list(), field_lists)
+        }
+        hir::Adt::Union(def) => {
+            let def = sema.source(def)?.value;
+            let field_lists = def
+                .record_field_list()
+                .map(ast::FieldList::RecordFieldList)
+                .into_iter()
+                .collect();
+            (def.generic_param_list(), field_lists)
+        }
+    };
+
+    let field_types = field_lists
+        .into_iter()
+        .flat_map(|fields| match fields {
+            ast::FieldList::RecordFieldList(fields) => fields
+                .fields()
+                .filter_map(|field| field.ty())
+                .collect::<Vec<_>>(),
+            ast::FieldList::TupleFieldList(fields) => {
+                fields.fields().filter_map(|field| field.ty()).collect()
+            }
+        })
+        .collect();
+    Some((generics, field_types))
+}
+
+/// Whether `adt` is the `unsafe_cell` lang item, i.e. `core::cell::UnsafeCell`
+fn is_unsafe_cell<'db, DB: HirDatabase>(adt: hir::Adt, sema: &Semantics<'db, DB>) -> bool {
+    let def = match adt {
+        hir::Adt::Struct(def) => sema.source(def),
+        _ => return false,
+    };
+    def.map_or(false, |def| {
+        def.value.attrs().any(|attr| {
+            attr.as_simple_key_value().map_or(false, |(key, value)| {
+                key == "lang" && value == "unsafe_cell"
+            })
+        })
+    })
+}
+
+pub(crate) fn is_lifetime(lifetime: Option<ast::Lifetime>, param: &str) -> bool {
+    lifetime.map_or(false, |lifetime| lifetime.to_string() == param)
+}
+
+/// Whether the generic parameter `param` occurs in `node`
+pub(crate) fn mentions(param: &str, node: &syntax::SyntaxNode) -> bool {
+    node.descendants().any(|node| {
+        ast::Lifetime::cast(node.clone()).map_or(false, |lifetime| lifetime.to_string() == param)
+            || ast::PathType::cast(node)
+                .and_then(|ty| ty.path())
+                .map_or(false, |path| {
+                    path.qualifier().is_none() && path.syntax().text() == param
+                })
+    })
+}
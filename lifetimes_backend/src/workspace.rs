@@ -0,0 +1,191 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use base_db::SourceDatabaseExt;
+use hir::{Crate, ModuleSource, Semantics};
+use ide::{AnalysisHost, FileId};
+use project_model::{CargoConfig, ProjectManifest, ProjectWorkspace, Sysroot};
+use rust_analyzer::cli::load_cargo::{load_workspace, LoadCargoConfig};
+use vfs::{AbsPathBuf, Vfs};
+
+use crate::{check_file, CheckError, CheckOptions, FunctionTrace};
+
+/// Selects what `check_workspace` loads and checks
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceOptions {
+    /// Only check the crates of this package. By default, every crate inside the workspace's directory is checked.
+    pub package: Option<String>,
+    /// The `library` directory of a rust-src checkout, used instead of the one of the installed toolchain
+    /// (e.g. a vendored copy for offline use)
+    pub sysroot_src: Option<PathBuf>,
+    /// How each file is checked. Function paths are relative to the crate root.
+    pub check: CheckOptions,
+}
+
+/// The result of checking a single file of a workspace
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// The name of the crate the file belongs to, if it has been loaded from a workspace
+    pub krate: Option<String>,
+    pub source: Arc<String>,
+    /// See `CheckOptions::trace`
+    pub traces: Vec<FunctionTrace>,
+    pub result: Result<(), CheckError>,
+}
+
+/// Loads the Cargo workspace of `manifest` (a `Cargo.toml`) with its dependencies and sysroot and checks every
+/// function of the selected crates. Returns one report per file, sorted by path. The report of a file whose check
+/// panicked has the error `CheckError::Panicked`.
+///
+/// If `options.sysroot_src` is given, failing to load it is an error. Otherwise, if no sysroot can be found, the
+/// workspace is loaded without one, so `std` won't resolve.
+pub fn check_workspace(
+    manifest: &Path,
+    options: &WorkspaceOptions,
+) -> Result<Vec<FileReport>, CheckError> {
+    let manifest = std::fs::canonicalize(manifest)
+        .map_err(|error| CheckError::Workspace(format!("{}: {}", manifest.display(), error)))?;
+    let workspace_dir = AbsPathBuf::assert(manifest.parent().unwrap().to_path_buf());
+    let sysroot_src = match &options.sysroot_src {
+        Some(sysroot_src) => Some(std::fs::canonicalize(sysroot_src).map_err(|error| {
+            CheckError::Workspace(format!("{}: {}", sysroot_src.display(), error))
+        })?),
+        None => None,
+    };
+
+    let (host, vfs) = match &sysroot_src {
+        Some(sysroot_src) => load(&manifest, Some(sysroot_src), true),
+        None => load(&manifest, None, true).or_else(|error| {
+            log::warn!("{}, loading the workspace without a sysroot", error);
+            load(&manifest, None, false)
+        }),
+    }
+    .map_err(CheckError::Workspace)?;
+
+    let db = host.raw_database();
+    let semantics = Semantics::new(db);
+
+    let path_of = |file: FileId| {
+        vfs.file_path(file)
+            .as_path()
+            .map(|path| AsRef::<Path>::as_ref(path).to_path_buf())
+    };
+
+    // The files to check with their crate's name and the path of their module
+    let mut files = HashMap::new();
+    for krate in Crate::all(db) {
+        let selected = match &options.package {
+            Some(package) => krate.display_name(db).map_or(false, |name| {
+                name.to_string().replace('-', "_") == package.replace('-', "_")
+            }),
+            None => {
+                let root_file = krate
+                    .root_module(db)
+                    .definition_source(db)
+                    .file_id
+                    .original_file(db);
+                vfs.file_path(root_file)
+                    .as_path()
+                    .map_or(false, |path| path.starts_with(&workspace_dir))
+            }
+        };
+        if !selected {
+            continue;
+        }
+        let crate_name = krate.display_name(db).map(|name| name.to_string());
+
+        for module in krate.modules(db) {
+            let source = module.definition_source(db);
+            // Inline modules are checked together with the file containing them
+            if let ModuleSource::SourceFile(_) = source.value {
+                let module_path = module
+                    .path_to_root(db)
+                    .iter()
+                    .rev()
+                    .filter_map(|module| module.name(db))
+                    .map(|name| name.to_string())
+                    .collect::<Vec<_>>();
+                files.insert(
+                    source.file_id.original_file(db),
+                    (crate_name.clone(), module_path),
+                );
+            }
+        }
+    }
+
+    let mut reports = files
+        .into_iter()
+        .filter_map(|(file, (krate, module_path))| {
+            let path = path_of(file)?;
+            let (traces, result) = panic::catch_unwind(AssertUnwindSafe(|| {
+                check_file(&semantics, file, &module_path, &options.check)
+            }))
+            .unwrap_or_else(|payload| {
+                (
+                    Vec::new(),
+                    Err(CheckError::Panicked(panic_message(payload))),
+                )
+            });
+            Some(FileReport {
+                path,
+                krate,
+                source: db.file_text(file),
+                traces,
+                result,
+            })
+        })
+        .collect::<Vec<_>>();
+    reports.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(reports)
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(
+            || "unknown panic".to_string(),
+            |message| message.to_string(),
+        ),
+    }
+}
+
+/// Loads the workspace of `manifest`. If `with_sysroot` is set, the sysroot's sources are loaded from `sysroot_src`
+/// or, by default, from the installed toolchain.
+fn load(
+    manifest: &Path,
+    sysroot_src: Option<&Path>,
+    with_sysroot: bool,
+) -> Result<(AnalysisHost, Vfs), String> {
+    // rust-analyzer only discovers the sysroot itself, one at a given path is loaded below
+    let cargo_config = CargoConfig {
+        no_sysroot: !with_sysroot || sysroot_src.is_some(),
+        ..CargoConfig::default()
+    };
+    let load_config = LoadCargoConfig {
+        load_out_dirs_from_check: false,
+        with_proc_macro: false,
+        prefill_caches: false,
+    };
+
+    let root = ProjectManifest::discover_single(&AbsPathBuf::assert(manifest.to_path_buf()))
+        .map_err(|error| format!("{:#}", error))?;
+    let mut workspace = ProjectWorkspace::load(root, &cargo_config, &|_| {})
+        .map_err(|error| format!("{:#}", error))?;
+    if let (true, Some(sysroot_src), ProjectWorkspace::Cargo { sysroot, .. }) =
+        (with_sysroot, sysroot_src, &mut workspace)
+    {
+        let loaded = Sysroot::load(&AbsPathBuf::assert(sysroot_src.to_path_buf()))
+            .map_err(|error| format!("{:#}", error))?;
+        *sysroot = Some(loaded);
+    }
+    let (host, vfs, _) =
+        load_workspace(workspace, &load_config, &|_| {}).map_err(|error| format!("{:#}", error))?;
+    Ok((host, vfs))
+}
@@ -74,8 +74,10 @@ fn move_value() {
 fn use_moved_value() {
     assert!(check(
         r#"
+        struct S;
+
         fn main() {
-            let x = 42;
+            let x = S;
             let y = x;
             x;
         }"#
@@ -88,8 +90,10 @@ fn use_moved_value() {
 fn borrow_moved_value() {
     assert!(check(
         r#"
+        struct S;
+
         fn main() {
-            let x = 42;
+            let x = S;
             let y = x;
             let z = &x;
         }"#
@@ -102,8 +106,10 @@ fn borrow_moved_value() {
 fn borrow_moved_value_mut() {
     assert!(check(
         r#"
+        struct S;
+
         fn main() {
-            let mut x = 42;
+            let mut x = S;
             let y = x;
             let z = &mut x;
         }"#
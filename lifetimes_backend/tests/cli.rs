@@ -0,0 +1,182 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const VALID: &str = r#"
+fn main() {
+    let mut x = 42;
+    let y = &mut x;
+    y;
+}
+"#;
+
+const INVALID: &str = r#"
+fn valid() {
+    let x = 1;
+    x;
+}
+
+fn invalid() {
+    let mut x = 42;
+    let y = &mut x;
+    let z = &mut x;
+    y;
+}
+"#;
+
+fn examples_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("lifetimes_cli").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn lifetimes(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lifetimes"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn exit_codes() {
+    let dir = examples_dir("exit_codes");
+    fs::write(dir.join("valid.rs"), VALID).unwrap();
+    fs::write(dir.join("invalid.rs"), INVALID).unwrap();
+
+    let valid = lifetimes(&["check", dir.join("valid.rs").to_str().unwrap()]);
+    assert_eq!(valid.status.code(), Some(0));
+
+    let invalid = lifetimes(&["check", dir.join("invalid.rs").to_str().unwrap()]);
+    assert_eq!(invalid.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&invalid.stderr).contains("error[E0499]"));
+
+    let both = lifetimes(&["check", dir.to_str().unwrap()]);
+    assert_eq!(both.status.code(), Some(1));
+}
+
+#[test]
+fn restrict_to_function() {
+    let dir = examples_dir("restrict_to_function");
+    let file = dir.join("invalid.rs");
+    fs::write(&file, INVALID).unwrap();
+
+    let output = lifetimes(&["check", file.to_str().unwrap(), "--function", "valid"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = lifetimes(&["check", file.to_str().unwrap(), "--function", "invalid"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn stdin_and_json() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lifetimes"))
+        .args(&["check", "-", "--format", "json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(INVALID.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let messages = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(messages.len(), 2);
+
+    assert_eq!(messages[0]["reason"], "compiler-message");
+    let message = &messages[0]["message"];
+    assert_eq!(message["level"], "error");
+    assert_eq!(message["code"]["code"], "E0499");
+    assert_eq!(message["spans"][0]["file_name"], "<stdin>");
+    assert_eq!(message["spans"][0]["is_primary"], true);
+    assert_eq!(message["spans"][0]["line_start"], 10);
+    assert!(message["rendered"]
+        .as_str()
+        .unwrap()
+        .starts_with("error[E0499]"));
+
+    assert_eq!(messages[1]["reason"], "build-finished");
+    assert_eq!(messages[1]["success"], false);
+}
+
+#[test]
+fn polonius_backend() {
+    let dir = examples_dir("polonius_backend");
+    let file = dir.join("invalid.rs");
+    fs::write(&file, INVALID).unwrap();
+
+    let output = lifetimes(&["check", file.to_str().unwrap(), "--backend", "polonius"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn trace() {
+    let dir = examples_dir("trace");
+    let file = dir.join("valid.rs");
+    fs::write(&file, VALID).unwrap();
+
+    let output = lifetimes(&["check", file.to_str().unwrap(), "--trace"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("fn main"));
+    assert!(stdout.contains("x: borrowed mutably by `y`"));
+}
+
+#[test]
+fn unsupported_code() {
+    let dir = examples_dir("unsupported_code");
+    let file = dir.join("unsupported.rs");
+    fs::write(&file, "fn main() {\n    loop {}\n}\n").unwrap();
+
+    let output = lifetimes(&["check", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn sarif() {
+    let dir = examples_dir("sarif");
+    fs::write(dir.join("valid.rs"), VALID).unwrap();
+    fs::write(dir.join("invalid.rs"), INVALID).unwrap();
+
+    let output = lifetimes(&["check", dir.to_str().unwrap(), "--format", "sarif"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["ruleId"], "Invalid");
+    assert_eq!(results[0]["properties"]["code"], "E0499");
+    assert!(
+        results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"]
+            .as_str()
+            .unwrap()
+            .ends_with("invalid.rs")
+    );
+}
+
+#[test]
+fn dot_at() {
+    let dir = examples_dir("dot_at");
+    let file = dir.join("valid.rs");
+    fs::write(&file, VALID).unwrap();
+
+    let output = lifetimes(&["check", file.to_str().unwrap(), "--dot-at", "4"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("digraph borrows {"));
+    assert!(stdout.contains("&mut"));
+    assert!(!stdout.contains("fn main"));
+}
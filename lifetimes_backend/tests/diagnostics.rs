@@ -0,0 +1,205 @@
+use lifetimes_backend::{check, CompilerMessage, Level, Target};
+
+#[test]
+fn invalidation_chain() {
+    let code = r#"fn main() {
+    let mut x = 42;
+    let y = &mut x;
+    let z = &mut x;
+    y;
+}"#;
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(error.code(), Some("E0499"));
+    assert!(error.render(code).contains(
+        "`y` borrows `x` mutably (line 3); \
+         `x` is borrowed mutably again (line 4), invalidating `y`; \
+         `y` used here (line 5)"
+    ));
+}
+
+#[test]
+fn shadowed_binding() {
+    let code = r#"fn main() {
+    let r;
+    {
+        let x = 1;
+        r = &x;
+        let x = 2;
+    }
+    r;
+}"#;
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(error.code(), Some("E0597"));
+    assert!(error
+        .render(code)
+        .contains("`x (shadowed, declared on line 4)` does not live long enough"));
+}
+
+#[test]
+fn transitive_invalidation_chain() {
+    let code = r#"fn main() {
+    let mut x = 1;
+    let y = &mut x;
+    let w = &y;
+    x = 2;
+    w;
+}"#;
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(error.code(), Some("E0506"));
+    assert!(error.render(code).contains(
+        "`y` borrows `x` mutably (line 3); \
+         `w` borrows `y` immutably (line 4); \
+         `x` is assigned to (line 5), invalidating `y`, which invalidates `w`; \
+         `w` used here (line 6)"
+    ));
+}
+
+#[test]
+fn closure_capture_used_while_borrowed() {
+    let code = r#"fn main() {
+    let mut x = 0;
+    let mut c = || x += 1;
+    x;
+    c();
+}"#;
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(error.code(), Some("E0503"));
+    assert_eq!(
+        error.to_string(),
+        "cannot use `x` because it was mutably borrowed"
+    );
+    assert!(error.render(code).contains(
+        "`c` borrows `x` mutably (line 3); \
+         `x` is used (line 4), invalidating `c`; \
+         `c` used here (line 5)"
+    ));
+}
+
+#[test]
+fn rustc_diagnostic() {
+    let code = r#"fn main() {
+    let mut x = 42;
+    let y = &mut x;
+    let z = &mut x;
+    y;
+}"#;
+    let diagnostic = check(code.to_string())
+        .unwrap_err()
+        .diagnostic("src/main.rs", code);
+
+    assert_eq!(diagnostic.level, Level::Error);
+    assert_eq!(diagnostic.code.as_ref().unwrap().code, "E0499");
+    assert!(diagnostic.code.unwrap().explanation.is_some());
+
+    let spans = diagnostic
+        .spans
+        .iter()
+        .map(|span| {
+            (
+                span.is_primary,
+                span.line_start,
+                span.label.as_deref().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        vec![
+            (true, 4, "second mutable borrow occurs here"),
+            (false, 3, "first mutable borrow occurs here"),
+            (false, 5, "borrow later used here, by `y`"),
+        ]
+    );
+    assert_eq!(diagnostic.spans[0].file_name, "src/main.rs");
+    assert_eq!(diagnostic.spans[0].text[0].text, "    let z = &mut x;");
+    assert_eq!(diagnostic.children[0].level, Level::Note);
+}
+
+#[test]
+fn cargo_json_shape() {
+    let code = "fn main() {\n    let x;\n    x;\n}\n";
+    let diagnostic = check(code.to_string())
+        .unwrap_err()
+        .diagnostic("src/main.rs", code);
+    let message = CompilerMessage::new(
+        "example 0.1.0 (path+file:///example)".to_string(),
+        "/example/Cargo.toml".to_string(),
+        Target::new("example".to_string(), "/example/src/main.rs".to_string()),
+        diagnostic,
+    );
+
+    let json = serde_json::to_value(&message).unwrap();
+    assert_eq!(json["reason"], "compiler-message");
+    assert_eq!(json["target"]["kind"][0], "bin");
+    assert_eq!(json["message"]["code"]["code"], "E0381");
+    assert_eq!(json["message"]["spans"][0]["byte_start"], 27);
+    assert_eq!(
+        json["message"]["spans"][0]["expansion"],
+        serde_json::Value::Null
+    );
+    assert!(json["message"]["children"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn unsupported_parameter_pattern() {
+    let code = "fn swap((a, b): (i32, i32)) -> (i32, i32) {\n    (b, a)\n}\n";
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(error.code(), None);
+    assert_eq!(
+        error.render(code),
+        "error: unsupported syntax: `(a, b): (i32, i32)`\n  --> line 1"
+    );
+}
+
+#[test]
+fn unsupported_operators() {
+    let code = "fn main() {\n    let a = 1;\n    let b = a + 1;\n}\n";
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(
+        error.render(code),
+        "error: unsupported syntax: `a + 1`\n  --> line 3"
+    );
+}
+
+#[test]
+fn unsupported_let_chain() {
+    let code =
+        "fn main() {\n    let x = Some(1);\n    if let Some(a) = x && let Some(b) = x {}\n}\n";
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(
+        error.render(code),
+        "error: unsupported syntax: `let Some(a) = x && let Some(b) = x`\n  --> line 3"
+    );
+}
+
+#[test]
+fn unresolved_path() {
+    let code = "fn main() {\n    let a = &missing;\n}\n";
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(
+        error.render(code),
+        "error: unsupported syntax: `missing`\n  --> line 2"
+    );
+}
+
+#[test]
+fn used_while_mutably_borrowed_is_explained() {
+    let code = r#"fn main() {
+    let mut x = 1;
+    let y = &mut x;
+    x;
+    y;
+}"#;
+    let error = check(code.to_string()).unwrap_err();
+
+    assert_eq!(error.code(), Some("E0503"));
+    assert!(error.explanation().is_some());
+}
@@ -0,0 +1,6 @@
+pub fn conflicting() {
+    let mut x = 42;
+    let y = &mut x;
+    let z = &mut x;
+    y;
+}
@@ -0,0 +1,8 @@
+mod borrows;
+mod std_types;
+
+fn main() {
+    let mut x = 42;
+    let y = &mut x;
+    y;
+}
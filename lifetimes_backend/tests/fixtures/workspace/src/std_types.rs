@@ -0,0 +1,9 @@
+use std::vec::Vec;
+
+pub fn push_while_borrowed() {
+    let mut v = Vec::new();
+    v.push(1);
+    let first = v.first();
+    v.push(2);
+    first;
+}
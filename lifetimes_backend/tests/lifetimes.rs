@@ -0,0 +1,172 @@
+use lifetimes_backend::{lifetimes, Glyph, LifetimeKind};
+
+#[test]
+fn local_and_loan_lifetimes() {
+    let (functions, result) = lifetimes(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            y;
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_ok());
+
+    let main = &functions[0];
+    assert_eq!(main.name, "main");
+
+    let x = &main.lifetimes[0];
+    assert_eq!((x.name.as_str(), x.kind), ("x", LifetimeKind::Local));
+    // `x` lives as long as the loan of it is used
+    assert_eq!((x.start_line, x.end_line), (2, 4));
+
+    let y = &main.lifetimes[1];
+    assert_eq!(y.name, "y");
+    assert_eq!((y.start_line, y.end_line), (3, 4));
+
+    let loan = &main.lifetimes[2];
+    assert_eq!(
+        (loan.name.as_str(), loan.kind),
+        ("&mut x", LifetimeKind::MutLoan)
+    );
+    assert_eq!((loan.start_line, loan.end_line), (3, 4));
+
+    assert_eq!(main.lanes, 3);
+    assert_eq!((x.lane, y.lane, loan.lane), (0, 1, 2));
+}
+
+#[test]
+fn lanes_are_reused() {
+    let (functions, result) = lifetimes(
+        r#"
+        fn main() {
+            let a = 1;
+            a;
+            let b = 2;
+            b;
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_ok());
+
+    let main = &functions[0];
+    assert_eq!(main.lanes, 1);
+    assert!(main.lifetimes.iter().all(|lifetime| lifetime.lane == 0));
+}
+
+#[test]
+fn invalidated_loan() {
+    let (functions, result) = lifetimes(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            let z = &mut x;
+            z;
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_ok());
+
+    let loans = functions[0]
+        .lifetimes
+        .iter()
+        .filter(|lifetime| lifetime.kind == LifetimeKind::MutLoan)
+        .collect::<Vec<_>>();
+    assert_eq!(loans.len(), 2);
+    // The first loan ends when it is last used, before the second one is created
+    assert_eq!((loans[0].start_line, loans[0].end_line), (3, 3));
+    assert_eq!(loans[0].invalidated_line, Some(4));
+    assert_eq!(loans[1].invalidated_line, None);
+    assert_eq!(loans[0].lane, loans[1].lane);
+}
+
+#[test]
+fn reservation_is_not_invalidated_by_shared_borrow() {
+    let (functions, result) = lifetimes(
+        r#"
+        struct Stack(usize);
+
+        impl Stack {
+            fn push(&mut self, value: usize) {}
+
+            fn len(&self) -> usize {
+                self.0
+            }
+        }
+
+        fn main() {
+            let mut v = Stack(0);
+            v.push(v.len());
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_ok());
+
+    let main = functions
+        .iter()
+        .find(|function| function.name == "main")
+        .unwrap();
+    let reservation = main
+        .lifetimes
+        .iter()
+        .find(|lifetime| lifetime.kind == LifetimeKind::MutLoan)
+        .unwrap();
+    assert_eq!(reservation.name, "&mut v");
+    assert_eq!(reservation.invalidated_line, None);
+}
+
+#[test]
+fn gutter_glyphs() {
+    let (functions, _) = lifetimes(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            x;
+        }"#
+        .to_string(),
+    );
+
+    let main = &functions[0];
+    let glyphs = |line: usize| {
+        main.gutter
+            .iter()
+            .find(|gutter_line| gutter_line.line == line)
+            .map(|gutter_line| {
+                gutter_line
+                    .cells
+                    .iter()
+                    .map(|cell| cell.glyph)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+    assert_eq!(glyphs(2), vec![Glyph::Start]);
+    assert_eq!(
+        glyphs(3),
+        vec![Glyph::Continue, Glyph::Single, Glyph::Single]
+    );
+    assert_eq!(glyphs(4), vec![Glyph::End]);
+}
+
+#[test]
+fn serializes_for_the_extension() {
+    let (functions, _) = lifetimes(
+        r#"
+        fn main() {
+            let x = 1;
+            x;
+        }"#
+        .to_string(),
+    );
+
+    let json = serde_json::to_value(&functions).unwrap();
+    let lifetime = &json[0]["lifetimes"][0];
+    assert_eq!(lifetime["name"], "x");
+    assert_eq!(lifetime["kind"], "local");
+    assert_eq!(lifetime["startLine"], 2);
+    assert_eq!(lifetime["endLine"], 3);
+    assert_eq!(json[0]["gutter"][0]["cells"][0]["glyph"], "start");
+}
@@ -0,0 +1,192 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde_json::{json, Value};
+
+/// Drives `lifetimes-lsp` with scripted JSON-RPC messages
+struct Client {
+    server: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_lifetimes-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = server.stdin.take().unwrap();
+        let stdout = BufReader::new(server.stdout.take().unwrap());
+        Self {
+            server,
+            stdin,
+            stdout,
+        }
+    }
+
+    fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        self.receive_until(|message| message["id"] == id)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn diagnostics(&mut self) -> Value {
+        self.receive_until(|message| message["method"] == "textDocument/publishDiagnostics")
+            ["params"]["diagnostics"]
+            .clone()
+    }
+
+    fn send(&mut self, message: Value) {
+        let content = message.to_string();
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive_until(&mut self, matches: impl Fn(&Value) -> bool) -> Value {
+        loop {
+            let message = self.receive();
+            if matches(&message) {
+                return message;
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(length) = header.strip_prefix("Content-Length: ") {
+                content_length = length.parse().unwrap();
+            }
+        }
+
+        let mut content = vec![0; content_length];
+        self.stdout.read_exact(&mut content).unwrap();
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    fn shutdown(mut self) {
+        self.request(1000, "shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.server.wait().unwrap().success());
+    }
+}
+
+const URI: &str = "file:///project/src/main.rs";
+
+#[test]
+fn diagnostics_and_ranges() {
+    let mut client = Client::start();
+    let initialized = client.request(1, "initialize", json!({ "capabilities": {} }));
+    assert_eq!(initialized["result"]["capabilities"]["textDocumentSync"], 1);
+    client.notify("initialized", json!({}));
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": URI,
+                "languageId": "rust",
+                "version": 1,
+                "text": "fn main() {\n    let mut x = 42;\n    let y = &mut x;\n    let z = &mut x;\n    y;\n}\n",
+            }
+        }),
+    );
+    let diagnostics = client.diagnostics();
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["code"], "E0499");
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 3);
+    assert_eq!(diagnostics[0]["source"], "lifetimes");
+
+    let ranges = client.request(
+        2,
+        "lifetimes/ranges",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let main = &ranges["result"][0];
+    assert_eq!(main["name"], "main");
+    assert_eq!(main["lifetimes"][0]["name"], "x");
+    assert!(main["lanes"].as_u64().unwrap() > 1);
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{
+                "text": "fn main() {\n    let mut x = 42;\n    let y = &mut x;\n    y;\n}\n",
+            }],
+        }),
+    );
+    assert!(client.diagnostics().as_array().unwrap().is_empty());
+
+    client.shutdown();
+}
+
+#[test]
+fn ranges_of_unknown_document() {
+    let mut client = Client::start();
+    client.request(1, "initialize", json!({ "capabilities": {} }));
+    client.notify("initialized", json!({}));
+
+    let response = client.request(
+        2,
+        "lifetimes/ranges",
+        json!({ "textDocument": { "uri": "file:///unknown.rs" } }),
+    );
+    assert!(response["error"].is_object());
+
+    client.shutdown();
+}
+
+#[test]
+fn closed_document_is_forgotten() {
+    let mut client = Client::start();
+    client.request(1, "initialize", json!({ "capabilities": {} }));
+    client.notify("initialized", json!({}));
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": URI,
+                "languageId": "rust",
+                "version": 1,
+                "text": "fn main() {\n    let x = 42;\n}\n",
+            }
+        }),
+    );
+    assert!(client.diagnostics().as_array().unwrap().is_empty());
+
+    client.notify(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert!(client.diagnostics().as_array().unwrap().is_empty());
+
+    let response = client.request(
+        2,
+        "lifetimes/ranges",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert!(response["error"].is_object());
+
+    client.shutdown();
+}
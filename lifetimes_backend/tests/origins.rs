@@ -0,0 +1,80 @@
+use lifetimes_backend::{origins, OriginKind};
+
+#[test]
+fn scopes_and_signature_lifetimes() {
+    let code = r#"fn main<'a, 'b: 'a>(a: i32)
+where
+    'a: 'static,
+{
+    let x = 1;
+    {
+        let y = 2;
+    }
+}"#;
+    let (functions, result) = origins(code.to_string());
+    assert!(result.is_ok());
+    let main = &functions[0];
+
+    let kinds = main
+        .origins
+        .iter()
+        .map(|origin| origin.kind.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            OriginKind::Static,
+            OriginKind::Parameters,
+            OriginKind::Signature("'a".to_string()),
+            OriginKind::Signature("'b".to_string()),
+            OriginKind::Block,
+            OriginKind::Block,
+        ]
+    );
+
+    let inner_block = &main.origins[5];
+    let nesting = main
+        .ancestors(inner_block.id)
+        .iter()
+        .map(|origin| origin.id)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        nesting,
+        vec![
+            inner_block.id,
+            main.origins[4].id,
+            main.origins[1].id,
+            main.origins[0].id
+        ]
+    );
+
+    // 'b: 'a and 'a: 'static
+    let a = &main.origins[2];
+    let b = &main.origins[3];
+    assert!(b.outlives.contains(&a.id));
+    assert!(a.outlives.contains(&main.origins[0].id));
+
+    assert_eq!(
+        main.to_text(code),
+        "#0 'static
+  #1 parameters (lines 1-9): a
+    #4 block (lines 4-9): x
+      #5 block (lines 6-8): y
+  #2 'a (line 1), outlives #1, #0
+  #3 'b (line 1), outlives #1, #2
+"
+    );
+}
+
+#[test]
+fn origins_as_dot() {
+    let code = "fn f<'a>(x: &'a i32) {\n    let y = x;\n}\n";
+    let (functions, result) = origins(code.to_string());
+    assert!(result.is_ok());
+
+    let dot = functions[0].to_dot(code);
+    assert!(dot.starts_with("digraph origins {"));
+    assert!(dot.contains("\"#0\" -> \"#1\";"));
+    assert!(dot.contains("\"#2\" -> \"#1\" [style=dashed, label=\"outlives\"];"));
+    assert!(dot.contains("label=\"#3 block (lines 1-3)\\ny\""));
+}
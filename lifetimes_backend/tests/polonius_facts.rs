@@ -0,0 +1,187 @@
+use std::{fs, path::PathBuf};
+
+use lifetimes_backend::{check_facts_dir, check_polonius, dump_facts};
+
+fn facts_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join("lifetimes_backend_facts")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn dump_layout() {
+    let dir = facts_dir("dump_layout");
+    dump_facts(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+        }
+
+        fn other() {
+            let z = 1;
+        }"#
+        .to_string(),
+        &dir,
+    )
+    .unwrap();
+
+    assert!(dir.join("main").join("loan_issued_at.facts").is_file());
+    assert!(dir.join("main").join("cfg_edge.facts").is_file());
+    assert!(dir.join("other").join("var_defined_at.facts").is_file());
+
+    let loans = fs::read_to_string(dir.join("main").join("loan_issued_at.facts")).unwrap();
+    assert_eq!(loans.lines().count(), 1);
+    assert!(loans.starts_with("\"'?"));
+}
+
+#[test]
+fn dump_functions_of_the_same_name() {
+    let dir = facts_dir("dump_functions_of_the_same_name");
+    dump_facts(
+        r#"
+        struct A;
+        struct B;
+
+        impl A {
+            fn new() {
+                let a = 1;
+            }
+        }
+
+        impl B {
+            fn new() {
+                let b = 1;
+            }
+        }
+
+        fn main() {
+            let x = 1;
+        }
+
+        mod inner {
+            fn main() {
+                let y = 1;
+            }
+        }"#
+        .to_string(),
+        &dir,
+    )
+    .unwrap();
+
+    let mut names = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names.len(), 4);
+    assert_eq!(names[..3], ["A-new", "B-new", "main"]);
+    assert!(names[3].starts_with("main-"));
+}
+
+#[test]
+fn dump_and_replay_error() {
+    let dir = facts_dir("dump_and_replay_error");
+    dump_facts(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            let z = &mut x;
+            y;
+        }"#
+        .to_string(),
+        &dir,
+    )
+    .unwrap();
+
+    assert!(check_facts_dir(&dir).is_err());
+}
+
+#[test]
+fn dump_and_replay_ok() {
+    let dir = facts_dir("dump_and_replay_ok");
+    dump_facts(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            let z = &mut x;
+            z;
+        }"#
+        .to_string(),
+        &dir,
+    )
+    .unwrap();
+
+    assert!(check_facts_dir(&dir).is_ok());
+}
+
+#[test]
+fn read_rustc_facts() {
+    let dir = facts_dir("read_rustc_facts");
+    let main = dir.join("main");
+    fs::create_dir_all(&main).unwrap();
+    fs::write(
+        main.join("loan_issued_at.facts"),
+        "\"'?2\"\t\"bw0\"\t\"Mid(bb0[1])\"\n",
+    )
+    .unwrap();
+    fs::write(
+        main.join("cfg_edge.facts"),
+        "\"Start(bb0[0])\"\t\"Mid(bb0[0])\"\n\
+         \"Mid(bb0[0])\"\t\"Start(bb0[1])\"\n\
+         \"Start(bb0[1])\"\t\"Mid(bb0[1])\"\n",
+    )
+    .unwrap();
+
+    assert!(check_facts_dir(&dir).is_ok());
+}
+
+#[test]
+fn polonius_multi_mut_borrow_used() {
+    assert!(check_polonius(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            let z = &mut x;
+            y;
+        }"#
+        .to_string()
+    )
+    .is_err());
+}
+
+#[test]
+fn polonius_multi_borrow_of_mutable() {
+    assert!(check_polonius(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &x;
+            let z = &x;
+            y;
+        }"#
+        .to_string()
+    )
+    .is_ok());
+}
+
+#[test]
+fn polonius_shadowed_local() {
+    // The shadowing `x` is a new local, assigning it doesn't invalidate the borrow of the shadowed one
+    assert!(check_polonius(
+        r#"
+        fn main() {
+            let x = 1;
+            let y = &x;
+            let x = 2;
+            y;
+        }"#
+        .to_string()
+    )
+    .is_ok());
+}
@@ -0,0 +1,103 @@
+use lifetimes_backend::{check, Diagnostic, DiagnosticSpan, DiagnosticSpanLine, Level};
+
+#[test]
+fn invalidated_borrow() {
+    let code = r#"fn main() {
+    let mut x = 42;
+    let y = &mut x;
+    let z = &mut x;
+    y;
+}"#;
+    let rendered = check(code.to_string())
+        .unwrap_err()
+        .diagnostic("src/main.rs", code)
+        .render(false);
+
+    assert_eq!(
+        rendered,
+        r#"error[E0499]: cannot borrow `x` as mutable more than once at a time
+ --> src/main.rs:4:13
+  |
+3 |     let y = &mut x;
+  |     --------------- first mutable borrow occurs here
+4 |     let z = &mut x;
+  |             ^^^^^^ second mutable borrow occurs here
+5 |     y;
+  |     - borrow later used here, by `y`
+  |
+  = note: `y` borrows `x` mutably (line 3); `x` is borrowed mutably again (line 4), invalidating `y`; `y` used here (line 5)"#
+    );
+}
+
+fn span(line: usize, start: usize, end: usize, is_primary: bool, label: &str) -> DiagnosticSpan {
+    let text = "    let r = f(&mut x, &x);";
+    DiagnosticSpan {
+        file_name: "lib.rs".to_string(),
+        byte_start: 0,
+        byte_end: 0,
+        line_start: line,
+        line_end: line,
+        column_start: start,
+        column_end: end,
+        is_primary,
+        text: vec![DiagnosticSpanLine {
+            text: text.to_string(),
+            highlight_start: start,
+            highlight_end: end,
+        }],
+        label: Some(label.to_string()),
+        suggested_replacement: None,
+        suggestion_applicability: None,
+        expansion: None,
+    }
+}
+
+fn diagnostic(spans: Vec<DiagnosticSpan>) -> Diagnostic {
+    Diagnostic {
+        message: "cannot borrow `x` as immutable because it is also borrowed as mutable"
+            .to_string(),
+        code: None,
+        level: Level::Error,
+        spans,
+        children: Vec::new(),
+        rendered: None,
+    }
+}
+
+#[test]
+fn labels_on_the_same_line() {
+    let rendered = diagnostic(vec![
+        span(12, 23, 25, true, "immutable borrow occurs here"),
+        span(12, 15, 21, false, "mutable borrow occurs here"),
+        span(12, 13, 14, false, "mutable borrow later used by call"),
+    ])
+    .render(false);
+
+    assert_eq!(
+        rendered,
+        r#"error: cannot borrow `x` as immutable because it is also borrowed as mutable
+  --> lib.rs:12:23
+   |
+12 |     let r = f(&mut x, &x);
+   |             - ------  ^^ immutable borrow occurs here
+   |             | |
+   |             | mutable borrow occurs here
+   |             |
+   |             mutable borrow later used by call"#
+    );
+}
+
+#[test]
+fn elided_lines_and_colors() {
+    let spans = vec![
+        span(20, 23, 25, true, "immutable borrow occurs here"),
+        span(3, 15, 21, false, "mutable borrow occurs here"),
+    ];
+
+    let rendered = diagnostic(spans.clone()).render(false);
+    assert!(rendered.contains("   |               ------ mutable borrow occurs here\n...\n20 |"));
+
+    let colored = diagnostic(spans).render(true);
+    assert!(colored.starts_with("\x1b[1;31merror\x1b[0m"));
+    assert!(colored.contains("\x1b[1;31m^\x1b[0m"));
+}
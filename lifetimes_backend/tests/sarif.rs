@@ -0,0 +1,72 @@
+//! Golden tests for the SARIF export.
+//!
+//! Every `.rs` file in `tests/sarif` is checked and the SARIF log is compared with the `.sarif` file next to it.
+//! Run with `BLESS=1` to update the `.sarif` files after an intended change.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use lifetimes_backend::{check, FileReport, SarifLog};
+
+#[test]
+fn golden_files() {
+    let sarif_dir = Path::new("tests").join("sarif");
+    let mut inputs = fs::read_dir(&sarif_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "rs")
+        })
+        .collect::<Vec<_>>();
+    inputs.sort();
+    assert!(!inputs.is_empty());
+
+    let mut mismatches = Vec::new();
+    for input in &inputs {
+        let source = fs::read_to_string(input).unwrap();
+        let report = FileReport {
+            path: input.clone(),
+            krate: None,
+            result: check(source.clone()),
+            source: Arc::new(source),
+            traces: Vec::new(),
+        };
+        let log = serde_json::to_string_pretty(&SarifLog::new(&[report], &[])).unwrap() + "\n";
+
+        let golden = input.with_extension("sarif");
+        if std::env::var_os("BLESS").is_some() {
+            fs::write(&golden, &log).unwrap();
+        } else if fs::read_to_string(&golden).ok().as_deref() != Some(log.as_str()) {
+            mismatches.push((golden, log));
+        }
+    }
+
+    for (golden, log) in &mismatches {
+        eprintln!("{} differs, the log is:\n{}", golden.display(), log);
+    }
+    assert!(
+        mismatches.is_empty(),
+        "{} golden files differ, run with BLESS=1 to update them",
+        mismatches.len()
+    );
+}
+
+#[test]
+fn unchecked_files() {
+    let log = SarifLog::new(&[], &[PathBuf::from("src/unsupported.rs")]);
+    let invocation = &log.runs[0].invocations[0];
+
+    assert!(!invocation.execution_successful);
+    assert_eq!(
+        invocation.tool_execution_notifications[0].locations[0]
+            .physical_location
+            .artifact_location
+            .uri,
+        "src/unsupported.rs"
+    );
+    assert!(log.runs[0].results.is_empty());
+}
@@ -0,0 +1,5 @@
+fn main() {
+    let mut x = 42;
+    let y = &mut x;
+    y;
+}
@@ -0,0 +1,151 @@
+use lifetimes_backend::Session;
+
+const PATH: &str = "/main.rs";
+
+const VALID: &str = r#"
+fn first() {
+    let mut x = 42;
+    let y = &mut x;
+    y;
+}
+
+fn second() {
+    let mut a = 1;
+    let b = &mut a;
+    b;
+}
+"#;
+
+#[test]
+fn rechecks_after_change() {
+    let mut session = Session::new();
+    session.set_file(PATH, VALID.to_string());
+    assert!(session.check(PATH).is_ok());
+
+    session.set_file(PATH, VALID.replace("    b;", "    let c = &mut a;\n    b;"));
+    assert!(session.check(PATH).is_err());
+
+    session.set_file(PATH, VALID.to_string());
+    assert!(session.check(PATH).is_ok());
+}
+
+#[test]
+fn only_changed_functions_are_checked() {
+    let mut session = Session::new();
+    session.set_file(PATH, VALID.to_string());
+    session.check(PATH).unwrap();
+    assert_eq!(session.functions_checked(), 2);
+
+    session.check(PATH).unwrap();
+    assert_eq!(session.functions_checked(), 2);
+
+    session.set_file(PATH, VALID.replace("let mut a = 1;", "let mut a = 2;"));
+    session.check(PATH).unwrap();
+    assert_eq!(session.functions_checked(), 3);
+}
+
+#[test]
+fn changed_items_recheck_functions() {
+    let mut session = Session::new();
+    let code = VALID.to_string() + "\nstruct Holder<'a> {\n    r: &'a u32,\n}\n";
+    session.set_file(PATH, code.clone());
+    session.check(PATH).unwrap();
+    assert_eq!(session.functions_checked(), 2);
+
+    session.set_file(PATH, code.replace("&'a u32", "&'a mut u32"));
+    session.check(PATH).unwrap();
+    assert_eq!(session.functions_checked(), 4);
+}
+
+#[test]
+fn removed_files_are_forgotten() {
+    let mut session = Session::new();
+    session.set_file(PATH, VALID.to_string());
+    session.set_file(
+        "/other.rs",
+        VALID.replace("    b;", "    let c = &mut a;\n    b;"),
+    );
+    session.check(PATH).unwrap();
+    assert_eq!(session.functions_checked(), 2);
+
+    session.remove_file(PATH);
+    assert!(!session.contains_file(PATH));
+    assert!(session.check("/other.rs").is_err());
+
+    // Reopening the file checks it again rather than using the results cached before it was closed
+    session.set_file(PATH, VALID.to_string());
+    session.check(PATH).unwrap();
+    assert_eq!(session.functions_checked(), 6);
+}
+
+#[test]
+fn unsupported_syntax_is_reported() {
+    let mut session = Session::new();
+    session.set_file(PATH, "fn swap((a, b): (u32, u32)) {}\n".to_string());
+    assert!(session.check(PATH).is_err());
+    assert!(session.check(PATH).is_err());
+}
+
+#[test]
+fn cached_results_move_with_their_function() {
+    let mut session = Session::new();
+    let invalid = VALID.replace("    b;", "    let c = &mut a;\n    b;");
+    session.set_file(PATH, invalid.clone());
+    let error = session.check(PATH).unwrap_err();
+    let line = line_of(&invalid, error.range().unwrap().start().into());
+
+    // Moves `second` down by two lines without changing it
+    let moved = invalid.replace("\nfn second", "\n\n\nfn second");
+    session.set_file(PATH, moved.clone());
+    let error = session.check(PATH).unwrap_err();
+    assert_eq!(session.functions_checked(), 2);
+    assert_eq!(
+        line_of(&moved, error.range().unwrap().start().into()),
+        line + 2
+    );
+
+    let (lifetimes, _) = session.lifetimes(PATH);
+    let a = lifetimes[1]
+        .lifetimes
+        .iter()
+        .find(|lifetime| lifetime.name == "a")
+        .unwrap();
+    assert_eq!(a.start_line, 10);
+}
+
+#[test]
+fn cached_shadowed_lines_move_with_their_function() {
+    let mut session = Session::new();
+    let code = r#"
+fn first() {}
+
+fn shadowing() {
+    let r;
+    {
+        let x = 1;
+        r = &x;
+        let x = 2;
+    }
+    r;
+}
+"#;
+    session.set_file(PATH, code.to_string());
+    let error = session.check(PATH).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`x (shadowed, declared on line 7)` does not live long enough"
+    );
+
+    // Moves `shadowing` down by two lines without changing it
+    session.set_file(PATH, code.replace("\nfn shadowing", "\n\n\nfn shadowing"));
+    let error = session.check(PATH).unwrap_err();
+    assert_eq!(session.functions_checked(), 2);
+    assert_eq!(
+        error.to_string(),
+        "`x (shadowed, declared on line 9)` does not live long enough"
+    );
+}
+
+fn line_of(code: &str, offset: usize) -> usize {
+    code[..offset].matches('\n').count() + 1
+}
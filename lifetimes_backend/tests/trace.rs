@@ -0,0 +1,107 @@
+use lifetimes_backend::{check_with_trace, Borrow, VarKind, VarStatus};
+
+#[test]
+fn snapshot_per_statement() {
+    let (traces, result) = check_with_trace(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            y;
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_ok());
+    assert_eq!(traces.len(), 1);
+    assert_eq!(traces[0].name, "main");
+
+    let statements = &traces[0].statements;
+    assert_eq!(statements.len(), 3);
+
+    let x = statements[0].var("x").unwrap();
+    assert_eq!(x.kind, VarKind::Local);
+    assert_eq!(x.status, VarStatus::Initialized);
+    assert!(statements[0].var("y").unwrap().status == VarStatus::Unitialized);
+
+    let x = statements[1].var("x").unwrap();
+    let y = statements[1].var("y").unwrap();
+    assert_eq!(x.status, VarStatus::MutBorrowed(y.id));
+    assert!(matches!(y.borrows.as_slice(), [Borrow::Mutable(_)]));
+    assert!(y.is_valid());
+}
+
+#[test]
+fn invalidated_var_in_trace() {
+    let (traces, result) = check_with_trace(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            let z = &mut x;
+            y;
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_err());
+
+    // The trace ends before the statement that failed to check
+    let statements = &traces[0].statements;
+    assert_eq!(statements.len(), 3);
+    assert!(statements[1].var("y").unwrap().is_valid());
+    assert!(!statements[2].var("y").unwrap().is_valid());
+    assert!(statements[2].var("z").unwrap().is_valid());
+}
+
+#[test]
+fn inner_scope_vars_are_not_live_after_block() {
+    let (traces, result) = check_with_trace(
+        r#"
+        fn main() {
+            let x = 1;
+            {
+                let y = 2;
+            }
+            x;
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_ok());
+
+    let statements = &traces[0].statements;
+    let inner = statements
+        .iter()
+        .find(|snapshot| snapshot.var("y").is_some())
+        .unwrap();
+    assert!(inner.var("x").is_some());
+    assert!(statements.last().unwrap().var("y").is_none());
+}
+
+#[test]
+fn borrow_graph() {
+    let (traces, result) = check_with_trace(
+        r#"
+        fn main() {
+            let mut x = 42;
+            let y = &mut x;
+            let z = &x;
+            let mut a = 1;
+            let m = &mut a;
+            let w;
+            let v = m;
+        }"#
+        .to_string(),
+    );
+    assert!(result.is_ok());
+
+    let statements = &traces[0].statements;
+    let dot = statements[1].to_dot();
+    assert!(dot.starts_with("digraph borrows {"));
+    assert!(dot.contains("subgraph cluster_0"));
+    assert!(dot.contains("label=\"&mut\", color=red, style=bold"));
+
+    let dot = statements[6].to_dot();
+    assert!(dot.contains(" y (invalid)\\ncannot borrow `x` as immutable"));
+    assert!(dot.contains(" w (uninitialized)\", shape=box, style=dashed"));
+    assert!(dot.contains(" m (moved)\", shape=box, style=filled"));
+    assert!(dot.contains("label=\"&\", color=blue"));
+}
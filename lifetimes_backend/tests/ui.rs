@@ -0,0 +1,179 @@
+//! Differential tests against rustc's borrow checker.
+//!
+//! Every `.rs` file below `tests/ui` is checked and the reported error is compared with the
+//! rustc-style annotations in the file (like in rustc's `src/test/ui`):
+//!
+//! ```text
+//! let z = &mut x; //~ ERROR E0499
+//! y;
+//! //~^^ ERROR E0499      (the `^`s refer to preceding lines)
+//! ```
+//!
+//! A file agrees with rustc if the checker reports exactly the annotated errors, with the same
+//! codes on the same lines. Files that are known to disagree with rustc are listed in
+//! `tests/ui/known-failures.txt`, including the files with more than one annotation, since the
+//! checker stops at the first error.
+
+use std::{
+    fs, panic,
+    path::{Path, PathBuf},
+};
+
+use lifetimes_backend::check;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Annotation {
+    line: usize,
+    code: Option<String>,
+}
+
+#[derive(Debug)]
+enum Outcome {
+    Pass,
+    Mismatch {
+        expected: Vec<Annotation>,
+        reported: Option<Annotation>,
+    },
+    Panicked,
+}
+
+#[test]
+fn ui() {
+    let ui_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("ui");
+    let known_failures = fs::read_to_string(ui_dir.join("known-failures.txt"))
+        .unwrap()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let mut files = Vec::new();
+    collect_files(&ui_dir, &mut files);
+    files.sort();
+
+    let mut passed = 0;
+    let mut regressions = Vec::new();
+    let mut fixed = Vec::new();
+
+    for file in &files {
+        let name = file
+            .strip_prefix(&ui_dir)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        let outcome = run_file(file);
+        let is_known_failure = known_failures.contains(&name);
+
+        match outcome {
+            Outcome::Pass => {
+                passed += 1;
+                if is_known_failure {
+                    fixed.push(name);
+                }
+            }
+            outcome => {
+                println!("{}: {:?}", name, outcome);
+                if !is_known_failure {
+                    regressions.push(name);
+                }
+            }
+        }
+    }
+
+    println!(
+        "ui: {} of {} files agree with rustc ({} known failures)",
+        passed,
+        files.len(),
+        known_failures.len()
+    );
+    for name in &fixed {
+        println!("{} passes now, remove it from known-failures.txt", name);
+    }
+
+    assert!(
+        regressions.is_empty(),
+        "files disagree with rustc: {:?}",
+        regressions
+    );
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+fn run_file(file: &Path) -> Outcome {
+    let code = fs::read_to_string(file).unwrap();
+    let expected = parse_annotations(&code);
+
+    let result = {
+        let code = code.clone();
+        panic::catch_unwind(move || check(code))
+    };
+
+    let reported = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(error)) => Some(Annotation {
+            line: error
+                .range()
+                .map(|range| line_of(&code, usize::from(range.start())))
+                .unwrap_or(0),
+            code: error.code().map(str::to_string),
+        }),
+        Err(_) => return Outcome::Panicked,
+    };
+
+    // `check` stops at the first error, so a file expecting more than one error can't agree with
+    // rustc and has to be listed in known-failures.txt
+    let agrees = expected.iter().eq(reported.iter());
+
+    if agrees {
+        Outcome::Pass
+    } else {
+        Outcome::Mismatch { expected, reported }
+    }
+}
+
+fn parse_annotations(code: &str) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    for (index, line) in code.lines().enumerate() {
+        let annotation = match line.find("//~") {
+            Some(start) => &line[start + 3..],
+            None => continue,
+        };
+        let carets = annotation.chars().take_while(|c| *c == '^').count();
+        let annotation = annotation[carets..].trim_start();
+        if !annotation.starts_with("ERROR") {
+            continue;
+        }
+
+        let code = annotation
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .find(|word| {
+                word.len() == 5
+                    && word.starts_with('E')
+                    && word[1..].chars().all(|c| c.is_ascii_digit())
+            })
+            .map(str::to_string);
+
+        annotations.push(Annotation {
+            line: index + 1 - carets,
+            code,
+        });
+    }
+    annotations.sort_by_key(|annotation| annotation.line);
+    annotations
+}
+
+fn line_of(code: &str, offset: usize) -> usize {
+    code[..offset].matches('\n').count() + 1
+}
@@ -0,0 +1,8 @@
+// Assigning to a local while a borrow of it is still live
+
+fn main() {
+    let mut x = 1;
+    let y = &x;
+    x = 2; //~ ERROR E0506
+    y;
+}
@@ -0,0 +1,6 @@
+// Reassigning an immutable local
+
+fn main() {
+    let x = 1;
+    x = 2; //~ ERROR E0384
+}
@@ -0,0 +1,6 @@
+// Mutably borrowing an immutable local
+
+fn main() {
+    let x = 1;
+    let y = &mut x; //~ ERROR E0596
+}
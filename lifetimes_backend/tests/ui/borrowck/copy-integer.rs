@@ -0,0 +1,7 @@
+// Integers are `Copy`, so they can still be used after being "moved"
+
+fn main() {
+    let x = 42;
+    let y = x;
+    x;
+}
@@ -0,0 +1,10 @@
+// A reference outlives the block its referent has been declared in
+
+fn main() {
+    let r;
+    {
+        let x = 0;
+        r = &x; //~ ERROR E0597
+    }
+    r;
+}
@@ -0,0 +1,16 @@
+// The value behind a shared reference can be copied out of it, but not moved
+
+struct Value {
+    n: u32,
+}
+
+fn copy(r: &u32) -> u32 {
+    let n = *r;
+    n
+}
+
+fn take(r: &Value) {
+    let v = *r; //~ ERROR E0507
+}
+
+fn main() {}
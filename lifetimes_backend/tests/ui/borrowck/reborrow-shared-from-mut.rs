@@ -0,0 +1,8 @@
+// A shared reborrow of a mutable reference
+
+fn main() {
+    let mut x = 1;
+    let y = &mut x;
+    let z = &*y;
+    z;
+}
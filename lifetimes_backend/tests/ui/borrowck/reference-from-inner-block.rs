@@ -0,0 +1,10 @@
+// Values and references to outer locals may leave a block
+
+fn main() {
+    let a = 1;
+    let r = {
+        let b = 2;
+        &a
+    };
+    r;
+}
@@ -0,0 +1,8 @@
+// Returning a reference to a local of the function's body
+
+fn dangling() -> &'static i32 {
+    let x = 0;
+    &x //~ ERROR E0515
+}
+
+fn main() {}
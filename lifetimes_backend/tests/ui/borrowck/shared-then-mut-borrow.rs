@@ -0,0 +1,8 @@
+// A shared borrow is still used after a mutable borrow of the same local
+
+fn main() {
+    let mut x = 1;
+    let y = &x;
+    let z = &mut x; //~ ERROR E0502
+    y;
+}
@@ -0,0 +1,7 @@
+// The first mutable borrow is dead before the second one is created
+
+fn main() {
+    let mut x = 1;
+    let y = &mut x;
+    let z = &mut x;
+}
@@ -0,0 +1,8 @@
+// Two mutable borrows of the same local that are both used
+
+fn main() {
+    let mut x = 1;
+    let y = &mut x;
+    let z = &mut x; //~ ERROR E0499
+    y;
+}
@@ -0,0 +1,8 @@
+// Mutable references are not `Copy`, so using one after moving it is an error
+
+fn main() {
+    let mut a = 1;
+    let x = &mut a;
+    let y = x;
+    x; //~ ERROR E0382
+}
@@ -0,0 +1,6 @@
+// Reading a declared but never initialized local
+
+fn main() {
+    let x: i32;
+    let y = x; //~ ERROR E0381
+}
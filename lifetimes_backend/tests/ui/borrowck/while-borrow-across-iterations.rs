@@ -0,0 +1,14 @@
+// A borrow stored in one iteration of a `while` loop is still held in the next one
+
+fn main() {
+    let c = 0;
+    let mut a = 1;
+    let mut r = &c;
+    let mut again = true;
+    while again {
+        let m = &mut a; //~ ERROR E0502
+        r;
+        r = &a;
+        again = false;
+    }
+}
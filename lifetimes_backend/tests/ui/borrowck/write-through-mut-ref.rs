@@ -0,0 +1,8 @@
+// Writing through a mutable reference that is dead afterwards
+
+fn main() {
+    let mut x = 1;
+    let y = &mut x;
+    *y = 2;
+    x;
+}
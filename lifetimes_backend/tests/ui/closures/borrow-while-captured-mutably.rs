@@ -0,0 +1,8 @@
+// A closure that mutates a local holds a unique borrow of it until its last call
+
+fn main() {
+    let mut v = 0;
+    let mut c = || v += 1;
+    let r = &v; //~ ERROR E0502
+    c();
+}
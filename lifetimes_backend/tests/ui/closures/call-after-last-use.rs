@@ -0,0 +1,9 @@
+// The unique borrow of a closure ends with its last call
+
+fn main() {
+    let mut x = 0;
+    let mut c = || x += 1;
+    c();
+    c();
+    x;
+}
@@ -0,0 +1,10 @@
+// A closure that only copies a local borrows it, so the local can't be assigned to while the closure is live
+
+fn main() {
+    let mut x = 0;
+    let c = || {
+        let y = x;
+    };
+    x = 5; //~ ERROR E0506
+    c();
+}
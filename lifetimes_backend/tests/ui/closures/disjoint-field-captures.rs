@@ -0,0 +1,14 @@
+// Closures capture the fields they use rather than the whole local, so two closures can mutate disjoint fields
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {
+    let mut p = Point { x: 0, y: 0 };
+    let mut c = || p.x += 1;
+    let mut d = || p.y += 1;
+    c();
+    d();
+}
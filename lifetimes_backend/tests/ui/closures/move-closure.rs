@@ -0,0 +1,8 @@
+// A `move` closure takes ownership of what it captures, and mutable references are not `Copy`
+
+fn main() {
+    let mut a = 1;
+    let r = &mut a;
+    let c = move || *r += 1;
+    r; //~ ERROR E0382
+}
@@ -0,0 +1,8 @@
+// A closure borrows what it assigns to uniquely, so using the local before calling the closure conflicts
+
+fn main() {
+    let mut x = 0;
+    let mut c = || x += 1;
+    x; //~ ERROR E0503
+    c();
+}
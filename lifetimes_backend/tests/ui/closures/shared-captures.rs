@@ -0,0 +1,10 @@
+// Closures that only read a local borrow it immutably, so the local can still be read
+
+fn main() {
+    let x = 1;
+    let c = || x + 1;
+    let d = || x + 2;
+    x;
+    c();
+    d();
+}
@@ -0,0 +1,16 @@
+// Values of type parameters bounded by `Copy` are copied
+
+fn pair<T>(a: T, b: T) {}
+
+fn twice<T: Copy>(t: T) {
+    pair(t, t);
+}
+
+fn twice_where<T>(t: T)
+where
+    T: Copy,
+{
+    pair(t, t);
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+// The signature's parameters are instantiated afresh at each call: the results borrow different vars
+
+fn id<T>(t: T) -> T {
+    t
+}
+
+fn main() {
+    let mut x = 1;
+    let mut y = 2;
+    let a = id(&mut x);
+    let b = id(&mut y);
+    a;
+    b;
+    x;
+}
@@ -0,0 +1,12 @@
+// A value of a type parameter may hold borrows: the result of `id(&x)` borrows `x`
+
+fn id<T>(t: T) -> T {
+    t
+}
+
+fn main() {
+    let mut x = 1;
+    let r = id(&x);
+    x = 2; //~ ERROR E0506
+    r;
+}
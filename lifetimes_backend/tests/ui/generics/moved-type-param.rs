@@ -0,0 +1,9 @@
+// Values of type parameters that aren't bounded by `Copy` are moved
+
+fn pair<T>(a: T, b: T) {}
+
+fn twice<T>(t: T) {
+    pair(t, t); //~ ERROR E0382
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+// `'b: 'a` lets a function return data of `'b` as `'a`, so the result may hold the borrows of `b` too
+
+fn pick<'a, 'b: 'a>(a: &'a u32, b: &'b u32) -> &'a u32 {
+    b
+}
+
+fn main() {
+    let x = 1;
+    let r;
+    {
+        let y = 2;
+        r = pick(&x, &y); //~ ERROR E0597
+    }
+    r;
+}
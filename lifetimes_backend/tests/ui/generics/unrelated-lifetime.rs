@@ -0,0 +1,15 @@
+// The result only holds the borrows of the arguments sharing a lifetime with the return type
+
+fn first<'a, 'b>(a: &'a u32, b: &'b u32) -> &'a u32 {
+    a
+}
+
+fn main() {
+    let x = 1;
+    let r;
+    {
+        let y = 2;
+        r = first(&x, &y);
+    }
+    r;
+}
@@ -0,0 +1,14 @@
+// The functions of inline modules are checked with the file containing them
+
+mod inner {
+    pub fn conflict() {
+        let mut x = 1;
+        let a = &mut x;
+        let b = &mut x; //~ ERROR E0499
+        a;
+    }
+}
+
+fn main() {
+    inner::conflict();
+}
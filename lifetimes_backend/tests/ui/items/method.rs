@@ -0,0 +1,17 @@
+// Methods are checked like free functions
+
+struct Counter {
+    n: u32,
+}
+
+impl Counter {
+    fn get(&self) -> u32 {
+        let counter = self;
+        let mut x = 1;
+        let a = &mut x;
+        let b = &mut x; //~ ERROR E0499
+        *a
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// A nested function is checked like a top-level one
+
+fn main() {
+    fn dangling() -> &'static u32 {
+        let x = 1;
+        &x //~ ERROR E0515
+    }
+
+    let y = 2;
+    y;
+}
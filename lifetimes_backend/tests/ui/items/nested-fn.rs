@@ -0,0 +1,19 @@
+// Items declared in a block don't borrow from it, nested functions are checked on their own
+
+struct Pair {
+    a: u32,
+    b: u32,
+}
+
+fn main() {
+    const LIMIT: u32 = 10;
+    struct Unused;
+
+    fn first(p: &Pair) -> &u32 {
+        &p.a
+    }
+
+    let p = Pair { a: 1, b: LIMIT };
+    let a = first(&p);
+    a;
+}
@@ -0,0 +1,15 @@
+// A non-reference pattern matching a reference binds by reference: `x` borrows `*r`, and through it `v`
+
+enum Slot {
+    Full(u32),
+    Empty,
+}
+
+fn main() {
+    let mut v = Slot::Full(1);
+    let r = &v;
+    if let Slot::Full(x) = r {
+        v = Slot::Empty; //~ ERROR E0506
+        x;
+    }
+}
@@ -0,0 +1,16 @@
+// The bindings of `if let` are dropped at the end of the branch, so the `else` branch may use the place again
+
+enum Slot {
+    Full(u32),
+    Empty,
+}
+
+fn main() {
+    let mut v = Slot::Full(1);
+    if let Slot::Full(ref mut x) = v {
+        x;
+    } else {
+        v = Slot::Empty;
+    }
+    v;
+}
@@ -0,0 +1,12 @@
+// Chains of `let`s in `if` and `while` conditions bind every pattern before the block
+
+fn main() {
+    let outer = Some(Some(1));
+    if let Some(inner) = outer && let Some(value) = inner {
+        value;
+    }
+    let mut pending = Some(1);
+    while let Some(value) = pending && value < 3 {
+        pending = None;
+    }
+}
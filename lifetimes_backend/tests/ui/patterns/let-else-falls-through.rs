@@ -0,0 +1,13 @@
+// The `else` block of `let ... else` must not fall through to the uses of the bindings
+
+enum Slot {
+    Full(u32),
+    Empty,
+}
+
+fn take(s: Slot) {
+    let Slot::Full(x) = s else {}; //~ ERROR E0308
+    x;
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+// What the `else` block of `let ... else` moves is still available after the `let`
+
+enum Slot {
+    Full(u32),
+    Empty,
+}
+
+fn main() {
+    let mut a = 1;
+    let r = &mut a;
+    let Slot::Full(x) = Slot::Empty else {
+        let moved = r;
+        return;
+    };
+    r;
+}
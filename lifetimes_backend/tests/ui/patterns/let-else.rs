@@ -0,0 +1,15 @@
+// The diverging `else` block of `let ... else` runs when the pattern doesn't match
+
+enum Slot {
+    Full(u32),
+    Empty,
+}
+
+fn take(s: &Slot) -> &u32 {
+    let Slot::Full(x) = s else {
+        return &0;
+    };
+    x
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// `ref mut` in a `let` pattern borrows the matched place mutably
+
+struct Pair {
+    a: u32,
+    b: u32,
+}
+
+fn main() {
+    let mut p = Pair { a: 1, b: 2 };
+    let Pair { ref mut a, .. } = p;
+    let q = &p; //~ ERROR E0502
+    a;
+}
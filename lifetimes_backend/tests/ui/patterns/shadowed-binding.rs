@@ -0,0 +1,12 @@
+// A shadowed binding is still a separate variable, its borrows outlive the shadowing
+
+fn main() {
+    let r;
+    {
+        let x = 1;
+        r = &x; //~ ERROR E0597
+        let x = 2;
+        x;
+    }
+    r;
+}
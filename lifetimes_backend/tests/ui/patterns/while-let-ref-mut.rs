@@ -0,0 +1,14 @@
+// The bindings of `while let` live in the loop's body
+
+enum Next {
+    Item(u32),
+    Done,
+}
+
+fn main() {
+    let mut n = Next::Item(1);
+    while let Next::Item(ref mut x) = n {
+        let r = &n; //~ ERROR E0502
+        x;
+    }
+}
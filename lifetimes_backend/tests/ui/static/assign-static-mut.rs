@@ -0,0 +1,12 @@
+// A `static mut` outlives the function, so it can't be assigned a borrow of a local
+
+static mut LAST: &u32 = &0;
+
+fn remember() {
+    let x = 1;
+    unsafe {
+        LAST = &x; //~ ERROR E0597
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// Every use of a `static mut` is the same place, so two mutable borrows of it conflict like for a local
+
+static mut COUNT: u32 = 0;
+
+fn bump(count: &mut u32) {}
+
+fn main() {
+    unsafe {
+        let a = &mut COUNT;
+        let b = &mut COUNT; //~ ERROR E0499
+        bump(a);
+    }
+}
@@ -0,0 +1,15 @@
+// `thread::spawn` requires its closure to be `'static`, so the closure must not hold a borrow of a local
+
+mod thread {
+    pub fn spawn<F: FnOnce() + Send + 'static>(f: F) {
+        f();
+    }
+}
+
+fn main() {
+    let v = 1;
+    let r = &v; //~ ERROR E0597
+    thread::spawn(move || {
+        r;
+    });
+}
@@ -0,0 +1,26 @@
+// Closures passed to `thread::spawn` may own their data or borrow `static` items and string literals
+
+mod thread {
+    pub fn spawn<F: FnOnce() + Send + 'static>(f: F) {
+        f();
+    }
+}
+
+static COUNT: u32 = 0;
+
+fn main() {
+    let v = 1;
+    thread::spawn(move || {
+        v;
+    });
+
+    let r = &COUNT;
+    thread::spawn(move || {
+        r;
+    });
+
+    let s = "message";
+    thread::spawn(move || {
+        s;
+    });
+}
@@ -0,0 +1,18 @@
+// A `&'static` parameter only accepts borrows of `static` and `const` items and string literals
+
+const LIMIT: u32 = 10;
+
+static COUNT: u32 = 0;
+
+fn keep(r: &'static u32) {}
+
+fn keep_str(s: &'static str) {}
+
+fn main() {
+    keep(&COUNT);
+    keep(&LIMIT);
+    keep_str("literal");
+
+    let x = 1;
+    keep(&x); //~ ERROR E0597
+}
@@ -0,0 +1,13 @@
+// Assigning to a field writes to the struct, which conflicts with the borrows of it
+
+struct Wrapper<'a> {
+    r: &'a u32,
+}
+
+fn main() {
+    let a = 1;
+    let mut w = Wrapper { r: &a };
+    let borrowed = &w;
+    w.r = &a; //~ ERROR E0506
+    borrowed;
+}
@@ -0,0 +1,12 @@
+// A struct holding a reference keeps the borrowed value borrowed for as long as the struct is used
+
+struct Wrapper<'a> {
+    r: &'a u32,
+}
+
+fn main() {
+    let mut x = 1;
+    let w = Wrapper { r: &x };
+    x = 2; //~ ERROR E0506
+    w;
+}
@@ -0,0 +1,18 @@
+// A field only takes the borrows held through the lifetime parameters of its type
+
+struct Pair<'a, 'b> {
+    n: u32,
+    a: &'a u32,
+    b: &'b u32,
+}
+
+fn main() {
+    let mut x = 1;
+    let y = 2;
+    let p = Pair { n: 0, a: &x, b: &y };
+    let n = p.n;
+    let b = p.b;
+    x = 3;
+    n;
+    b;
+}
@@ -0,0 +1,15 @@
+// A struct holds the borrows of the values assigned to its fields
+
+struct Wrapper<'a> {
+    r: &'a u32,
+}
+
+fn main() {
+    let a = 1;
+    let mut w = Wrapper { r: &a };
+    {
+        let b = 2;
+        w.r = &b; //~ ERROR E0597
+    }
+    w;
+}
@@ -0,0 +1,13 @@
+// Reading a reference out of a struct keeps the borrow alive after the struct's last use
+
+struct Wrapper<'a> {
+    r: &'a u32,
+}
+
+fn main() {
+    let mut x = 1;
+    let w = Wrapper { r: &x };
+    let r = w.r;
+    x = 2; //~ ERROR E0506
+    r;
+}
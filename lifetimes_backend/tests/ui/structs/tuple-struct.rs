@@ -0,0 +1,11 @@
+// Tuple structs hold the borrows passed to their constructor, until their last use
+
+struct Ref<'a>(&'a u32);
+
+fn main() {
+    let mut x = 1;
+    let r = Ref(&x);
+    r;
+    x = 2;
+    x;
+}
@@ -0,0 +1,14 @@
+// Tuple variants hold the mutable borrows passed to their constructor
+
+enum Slot<'a> {
+    Empty,
+    Full(&'a mut u32),
+}
+
+fn main() {
+    let mut x = 1;
+    let slot = Slot::Full(&mut x);
+    let empty = Slot::Empty;
+    x; //~ ERROR E0503
+    slot;
+}
@@ -0,0 +1,14 @@
+// Temporaries of a block's tail expression live until the end of the statement enclosing the block
+
+fn make() -> u32 {
+    0
+}
+
+fn same(x: &u32) -> &u32 {
+    x
+}
+
+fn main() {
+    let r = { same(&make()) }; //~ ERROR E0716
+    r;
+}
@@ -0,0 +1,14 @@
+// A temporary that is only borrowed through a call's argument is dropped at the end of the `let`
+
+fn make() -> u32 {
+    0
+}
+
+fn same(x: &u32) -> &u32 {
+    x
+}
+
+fn main() {
+    let r = same(&make()); //~ ERROR E0716
+    r;
+}
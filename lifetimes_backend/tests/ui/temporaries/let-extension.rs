@@ -0,0 +1,12 @@
+// Borrowing a temporary in a `let` initializer (or in the tail of a block there) extends it to the end of the block
+
+fn make() -> u32 {
+    0
+}
+
+fn main() {
+    let r = &make();
+    let s = { &make() };
+    r;
+    s;
+}
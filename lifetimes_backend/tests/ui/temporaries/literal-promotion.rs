@@ -0,0 +1,9 @@
+// Borrowed literals are promoted to constants, so they outlive every block
+
+fn main() {
+    let r;
+    {
+        r = &1;
+    }
+    r;
+}
@@ -0,0 +1,11 @@
+// The elided bound of `Box<dyn Show>` defaults to `'static`, which the borrowed parameter doesn't satisfy
+
+trait Show {}
+
+impl Show for &u32 {}
+
+fn boxed(x: &u32) -> Box<dyn Show> {
+    Box::new(x) //~ ERROR E0759
+}
+
+fn main() {}
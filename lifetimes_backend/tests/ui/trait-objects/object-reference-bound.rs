@@ -0,0 +1,17 @@
+// The elided bound of `&'a dyn Show` defaults to `'a`, an explicit bound may be shorter than `'static`
+
+trait Show {}
+
+impl Show for u32 {}
+
+impl Show for &u32 {}
+
+fn show<'a>(x: &'a u32) -> &'a dyn Show {
+    x
+}
+
+fn boxed<'a>(x: &'a u32) -> Box<dyn Show + 'a> {
+    Box::new(x)
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+// An `impl Trait` without a lifetime bound must not hold the data of a parameter: the closure captures `x`
+
+fn reader(x: &u32) -> impl Fn() -> u32 {
+    move || *x //~ ERROR E0759
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+// `+ '_` lets the opaque type capture the elided lifetime of the parameter
+
+fn reader(x: &u32) -> impl Fn() -> u32 + '_ {
+    move || *x
+}
+
+fn main() {}
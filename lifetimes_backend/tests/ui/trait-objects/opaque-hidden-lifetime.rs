@@ -0,0 +1,11 @@
+// The hidden type `&'x u32` captures `'x`, which doesn't appear in the bounds of `impl Trait<'y>`
+
+trait Trait<'a> {}
+
+impl<'a, 'b> Trait<'b> for &'a u32 {}
+
+fn pick<'x, 'y>(x: &'x u32, y: &'y u32) -> impl Trait<'y> {
+    x //~ ERROR E0700
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+// Only shared reads are allowed during the reservation, not another mutable borrow
+
+struct Stack(usize);
+
+impl Stack {
+    fn push(&mut self, value: usize) {}
+
+    fn pop(&mut self) -> usize {
+        self.0
+    }
+}
+
+fn push_popped(mut v: Stack) {
+    v.push(v.pop()); //~ ERROR E0499
+}
+
+fn main() {}
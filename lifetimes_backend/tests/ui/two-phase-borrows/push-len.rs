@@ -0,0 +1,17 @@
+// The `&mut v` auto-ref of `push` is only activated at the call, so its argument may still read `v`
+
+struct Stack(usize);
+
+impl Stack {
+    fn push(&mut self, value: usize) {}
+
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+fn push_len(mut v: Stack) {
+    v.push(v.len());
+}
+
+fn main() {}
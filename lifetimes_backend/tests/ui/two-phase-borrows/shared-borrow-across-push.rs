@@ -0,0 +1,19 @@
+// A shared borrow that is still used after the call conflicts with the activated auto-ref
+
+struct Stack(usize);
+
+impl Stack {
+    fn push(&mut self, value: usize) {}
+
+    fn peek(&self) -> &usize {
+        &self.0
+    }
+}
+
+fn keep_top(mut v: Stack) {
+    let top = v.peek();
+    v.push(1); //~ ERROR E0502
+    top;
+}
+
+fn main() {}
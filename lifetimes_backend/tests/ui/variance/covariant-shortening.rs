@@ -0,0 +1,22 @@
+// Shared references and the structs holding them are covariant: their lifetime is shortened to the call
+
+struct Wrapper<'a> {
+    r: &'a u32,
+}
+
+fn read<'a>(r: &&'a u32, v: &'a u32) {}
+
+fn look<'a>(w: &Wrapper<'a>, v: &'a u32) {}
+
+fn main() {
+    let x = 1;
+    let r = &x;
+    let w = Wrapper { r: &x };
+    {
+        let y = 2;
+        read(&r, &y);
+        look(&w, &y);
+    }
+    r;
+    w;
+}
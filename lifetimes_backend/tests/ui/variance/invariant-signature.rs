@@ -0,0 +1,15 @@
+// A callee whose signature is invariant in `'a` may store `v` in the place `r` borrows
+
+fn assign<'a>(r: &mut &'a u32, v: &'a u32) {
+    *r = v;
+}
+
+fn main() {
+    let x = 1;
+    let mut r = &x;
+    {
+        let y = 2;
+        assign(&mut r, &y); //~ ERROR E0597
+    }
+    r;
+}
@@ -0,0 +1,21 @@
+// Types holding a `*mut T` (like `Cell<T>`) are invariant in `T`, their lifetime can't be shortened to the value's
+
+struct Cell<T> {
+    value: *mut T,
+}
+
+fn cell<'a>(value: &'a u32) -> Cell<&'a u32> {
+    cell(value)
+}
+
+fn put<'a>(cell: &Cell<&'a u32>, value: &'a u32) {}
+
+fn main() {
+    let x = 1;
+    let c = cell(&x);
+    {
+        let y = 2;
+        put(&c, &y); //~ ERROR E0597
+    }
+    c;
+}
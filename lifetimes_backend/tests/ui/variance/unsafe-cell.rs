@@ -0,0 +1,27 @@
+// `Cell<T>` is built on `UnsafeCell<T>` like std's, which is invariant in `T` although its field is a plain `T`.
+// The `unsafe_cell` lang item stands in for core's, as the corpus is checked without a sysroot.
+
+#[lang = "unsafe_cell"]
+struct UnsafeCell<T> {
+    value: T,
+}
+
+struct Cell<T> {
+    value: UnsafeCell<T>,
+}
+
+fn cell<'a>(value: &'a u32) -> Cell<&'a u32> {
+    cell(value)
+}
+
+fn put<'a>(cell: &Cell<&'a u32>, value: &'a u32) {}
+
+fn main() {
+    let x = 1;
+    let c = cell(&x);
+    {
+        let y = 2;
+        put(&c, &y); //~ ERROR E0597
+    }
+    c;
+}
@@ -0,0 +1,12 @@
+// `&mut T` is invariant in `T`: writing a shorter-lived reference through `&mut &T` doesn't shorten the place
+
+fn main() {
+    let x = 1;
+    let mut r = &x;
+    {
+        let y = 2;
+        let m = &mut r;
+        *m = &y; //~ ERROR E0597
+    }
+    r;
+}
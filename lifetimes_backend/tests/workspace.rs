@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use lifetimes_backend::{check_workspace, CheckError, WorkspaceOptions};
+
+fn fixture_manifest() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("workspace")
+        .join("Cargo.toml")
+}
+
+#[test]
+fn checks_every_module() {
+    let reports = check_workspace(&fixture_manifest(), &WorkspaceOptions::default()).unwrap();
+
+    let names = reports
+        .iter()
+        .map(|report| {
+            report
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["borrows.rs", "main.rs", "std_types.rs"]);
+
+    let error = reports[0].result.as_ref().unwrap_err();
+    assert_eq!(error.code(), Some("E0499"));
+    assert!(reports[1].result.is_ok());
+}
+
+#[test]
+fn resolves_std() {
+    let reports = check_workspace(&fixture_manifest(), &WorkspaceOptions::default()).unwrap();
+
+    // The conflict is only found if the signatures of `Vec::first` and `Vec::push` resolve
+    let report = reports
+        .iter()
+        .find(|report| report.path.ends_with("std_types.rs"))
+        .unwrap();
+    let error = report.result.as_ref().unwrap_err();
+    assert_eq!(error.code(), Some("E0502"));
+}
+
+#[test]
+fn selects_package() {
+    let options = WorkspaceOptions {
+        package: Some("workspace-fixture".to_string()),
+        ..WorkspaceOptions::default()
+    };
+    assert_eq!(
+        check_workspace(&fixture_manifest(), &options)
+            .unwrap()
+            .len(),
+        3
+    );
+
+    let options = WorkspaceOptions {
+        package: Some("unknown".to_string()),
+        ..WorkspaceOptions::default()
+    };
+    assert!(check_workspace(&fixture_manifest(), &options)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn invalid_sysroot_src() {
+    // An existing directory that isn't a sysroot isn't silently replaced by no sysroot
+    let options = WorkspaceOptions {
+        sysroot_src: Some(fixture_manifest().parent().unwrap().to_path_buf()),
+        ..WorkspaceOptions::default()
+    };
+    assert!(matches!(
+        check_workspace(&fixture_manifest(), &options),
+        Err(CheckError::Workspace(_))
+    ));
+}
+
+#[test]
+fn missing_manifest() {
+    assert!(matches!(
+        check_workspace(
+            Path::new("does/not/exist/Cargo.toml"),
+            &WorkspaceOptions::default()
+        ),
+        Err(CheckError::Workspace(_))
+    ));
+}